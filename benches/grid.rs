@@ -0,0 +1,88 @@
+use battleship::grid::{CellState, Coordinate, Grid};
+use battleship::ship::Ship;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Size of the benchmarked grid.
+const GRID_SIZE: u8 = 26;
+
+/// Returns all the coordinates on the benchmarked grid.
+fn coordinates() -> Vec<Coordinate> {
+    (1..=GRID_SIZE)
+        .flat_map(|y| (1..=GRID_SIZE).map(move |x| Coordinate::from((x, y))))
+        .collect()
+}
+
+/// Resolves the cell state by scanning every ship (without the cell index).
+fn linear_cell_state(ships: &[Ship], misses: &[Coordinate], coordinate: Coordinate) -> CellState {
+    if let Some(ship) = ships.iter().find(|ship| ship.coords.contains(&coordinate)) {
+        if ship.is_sunk() {
+            CellState::Sunk
+        } else if ship
+            .coords
+            .iter()
+            .find(|c| *c == &coordinate)
+            .map(|c| c.is_hit)
+            == Some(true)
+        {
+            CellState::Hit
+        } else {
+            CellState::Unknown
+        }
+    } else if misses.contains(&coordinate) {
+        CellState::Miss
+    } else {
+        CellState::Unknown
+    }
+}
+
+fn cell_state(c: &mut Criterion) {
+    let mut grid = Grid::new_random(GRID_SIZE, GRID_SIZE);
+    let coordinates = coordinates();
+    coordinates.iter().step_by(3).for_each(|coord| {
+        grid.shoot(*coord);
+    });
+    let misses = grid.misses();
+    let mut group = c.benchmark_group("cell_state");
+    group.bench_function("linear", |b| {
+        b.iter(|| {
+            coordinates.iter().for_each(|coord| {
+                black_box(linear_cell_state(grid.ships(), &misses, *coord));
+            })
+        })
+    });
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            coordinates.iter().for_each(|coord| {
+                black_box(grid.cell_state(*coord));
+            })
+        })
+    });
+    group.finish();
+}
+
+fn display(c: &mut Criterion) {
+    let grid = Grid::new_random(GRID_SIZE, GRID_SIZE);
+    c.bench_function("display", |b| {
+        b.iter(|| black_box(grid.as_string(false).expect("failed to display grid")))
+    });
+}
+
+fn self_play(c: &mut Criterion) {
+    let coordinates = coordinates();
+    c.bench_function("self_play", |b| {
+        b.iter(|| {
+            let mut grid = Grid::new_random(GRID_SIZE, GRID_SIZE);
+            let mut shots = coordinates.clone();
+            fastrand::shuffle(&mut shots);
+            for coord in shots {
+                grid.shoot(coord);
+                if grid.is_cleared() {
+                    break;
+                }
+            }
+        })
+    });
+}
+
+criterion_group!(benches, cell_state, display, self_play);
+criterion_main!(benches);
@@ -0,0 +1,307 @@
+//! Player accounts.
+
+use crate::{secret_eq, Result};
+use pbkdf2::pbkdf2_hmac;
+use sha2::Sha256;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+/// Rating of the new accounts.
+pub const DEFAULT_RATING: i32 = 1200;
+/// Maximum change of the rating after a game.
+const K_FACTOR: f64 = 32.0;
+/// Number of the hashing rounds for the passwords.
+///
+/// Tests use fewer rounds since hashing is slow by design.
+const HASH_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+/// Length of the password salt in bytes.
+const SALT_LENGTH: usize = 16;
+/// The prefix of the comment lines in an accounts file.
+const COMMENT_PREFIX: char = ';';
+
+/// Returns the given bytes as a lowercase hexadecimal string.
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Hashes the password with the given salt using PBKDF2-HMAC-SHA256.
+///
+/// The hash is repeated for [`HASH_ROUNDS`] times to slow down brute forcing.
+pub fn hash_password(salt: &str, password: &str) -> String {
+    let mut hash = [0; 32];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt.as_bytes(), HASH_ROUNDS, &mut hash);
+    to_hex(&hash)
+}
+
+/// Returns the new Elo ratings of the winner and the loser.
+pub fn update_ratings(winner: i32, loser: i32) -> (i32, i32) {
+    let expected = 1.0 / (1.0 + 10_f64.powf(f64::from(loser - winner) / 400.0));
+    let change = (K_FACTOR * (1.0 - expected)).round() as i32;
+    (winner + change, loser - change)
+}
+
+/// Registered player account.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Account {
+    /// Name of the player.
+    pub name: String,
+    /// Random salt of the password hash.
+    salt: String,
+    /// Salted hash of the password.
+    hash: String,
+    /// Number of the games won.
+    pub wins: u32,
+    /// Number of the games lost.
+    pub losses: u32,
+    /// Elo rating.
+    pub rating: i32,
+}
+
+/// Display the account as a line of the accounts file.
+///
+/// Name is the last field since it might contain spaces.
+impl fmt::Display for Account {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {} {} {}",
+            self.rating, self.wins, self.losses, self.salt, self.hash, self.name
+        )
+    }
+}
+
+/// Parses the account from a line of the accounts file.
+impl FromStr for Account {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let mut parts = value.trim().splitn(6, ' ');
+        let mut next = || parts.next().ok_or("missing field");
+        Ok(Self {
+            rating: next()?.parse()?,
+            wins: next()?.parse()?,
+            losses: next()?.parse()?,
+            salt: next()?.to_string(),
+            hash: next()?.to_string(),
+            name: next()?.to_string(),
+        })
+    }
+}
+
+impl Account {
+    /// Constructs a new instance of [`Account`] with a random salt.
+    ///
+    /// Salt is taken from the random number generator of the operating system.
+    /// Password is hashed here, so this should be called without holding any locks.
+    pub fn new(name: &str, password: &str) -> Result<Self> {
+        if password.is_empty() {
+            return Err("password cannot be empty".into());
+        }
+        let mut salt = [0; SALT_LENGTH];
+        getrandom::getrandom(&mut salt)?;
+        let salt = to_hex(&salt);
+        Ok(Self {
+            name: name.to_string(),
+            hash: hash_password(&salt, password),
+            salt,
+            wins: 0,
+            losses: 0,
+            rating: DEFAULT_RATING,
+        })
+    }
+
+    /// Returns whether if the password is correct.
+    ///
+    /// Hashes are compared in constant time.
+    pub fn verify(&self, password: &str) -> bool {
+        secret_eq(&hash_password(&self.salt, password), &self.hash)
+    }
+}
+
+/// File-backed store of the player accounts.
+///
+/// An accounts file consists of lines in the form of
+/// `<rating> <wins> <losses> <salt> <hash> <name>`.
+/// Lines starting with `;` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Accounts {
+    /// Path of the accounts file.
+    path: Option<PathBuf>,
+    /// Registered accounts.
+    accounts: Vec<Account>,
+}
+
+/// Display the accounts in the accounts file format.
+impl fmt::Display for Accounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} <rating> <wins> <losses> <salt> <hash> <name>",
+            COMMENT_PREFIX
+        )?;
+        for account in &self.accounts {
+            writeln!(f, "{}", account)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the accounts from the contents of an accounts file.
+impl FromStr for Accounts {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let accounts = value
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                !line.trim().is_empty() && !line.trim_start().starts_with(COMMENT_PREFIX)
+            })
+            .map(|(i, line)| {
+                Account::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e).into())
+            })
+            .collect::<Result<Vec<Account>>>()?;
+        Ok(Self {
+            path: None,
+            accounts,
+        })
+    }
+}
+
+impl Accounts {
+    /// Loads the accounts from the given file.
+    ///
+    /// Changes are saved to the same file. The file is created if it does not exist.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut accounts = match fs::read_to_string(&path) {
+            Ok(contents) => contents.parse::<Self>().map_err(|e| {
+                format!(
+                    "Failed to load accounts from {}: {}",
+                    path.as_ref().display(),
+                    e
+                )
+            })?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(e.into()),
+        };
+        accounts.path = Some(path.as_ref().to_path_buf());
+        Ok(accounts)
+    }
+
+    /// Saves the accounts to the file that they are loaded from.
+    pub fn save(&self) -> Result<()> {
+        if let Some(path) = &self.path {
+            let temp_path = path.with_extension("tmp");
+            fs::write(&temp_path, self.to_string())?;
+            fs::rename(temp_path, path)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the account with the given name (ignoring case).
+    pub fn get(&self, name: &str) -> Option<&Account> {
+        let name = name.to_lowercase();
+        self.accounts
+            .iter()
+            .find(|account| account.name.to_lowercase() == name)
+    }
+
+    /// Registers a new account and saves the accounts.
+    ///
+    /// See [`Account::new`].
+    pub fn register(&mut self, account: Account) -> Result<()> {
+        if self.get(&account.name).is_some() {
+            return Err(format!("{} is already registered", account.name).into());
+        }
+        self.accounts.push(account);
+        self.save()
+    }
+
+    /// Updates the records and ratings of the players and saves the accounts.
+    ///
+    /// Players without an account are rated as [`DEFAULT_RATING`].
+    pub fn record_game(&mut self, winner: &str, loser: &str) -> Result<()> {
+        let rating = |name: &str| {
+            self.get(name)
+                .map(|account| account.rating)
+                .unwrap_or(DEFAULT_RATING)
+        };
+        let (winner_rating, loser_rating) = update_ratings(rating(winner), rating(loser));
+        for account in self.accounts.iter_mut() {
+            if account.name == winner {
+                account.wins += 1;
+                account.rating = winner_rating;
+            } else if account.name == loser {
+                account.losses += 1;
+                account.rating = loser_rating;
+            }
+        }
+        self.save()
+    }
+
+    /// Returns the leaderboard of the accounts that are ordered by rating.
+    pub fn leaderboard(&self) -> String {
+        let mut accounts = self.accounts.iter().collect::<Vec<&Account>>();
+        accounts.sort_by(|a, b| b.rating.cmp(&a.rating).then(b.wins.cmp(&a.wins)));
+        if accounts.is_empty() {
+            return String::from("No players are registered yet.\n");
+        }
+        accounts
+            .iter()
+            .enumerate()
+            .map(|(i, account)| {
+                format!(
+                    "{}. {} ({}, {}W/{}L)\n",
+                    i + 1,
+                    account.name,
+                    account.rating,
+                    account.wins,
+                    account.losses
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_accounts() -> Result<()> {
+        let account = Account::new("orhun", "hunter2")?;
+        assert!(account.verify("hunter2"));
+        assert!(!account.verify("hunter3"));
+        assert_ne!(account.hash, Account::new("orhun", "hunter2")?.hash);
+        assert_eq!(account, account.to_string().parse()?);
+        assert_eq!((1216, 1184), update_ratings(1200, 1200));
+        assert_eq!((1224, 1376), update_ratings(1200, 1400));
+
+        let path = env::temp_dir().join(format!("battleship-accounts-{}", fastrand::u64(..)));
+        let mut accounts = Accounts::load(&path)?;
+        accounts.register(Account::new("orhun", "hunter2")?)?;
+        accounts.register(Account::new("alice bob", "secret")?)?;
+        assert!(accounts.register(Account::new("orhun", "other")?).is_err());
+        assert!(accounts.register(Account::new("Orhun", "other")?).is_err());
+        assert_eq!(
+            Some("orhun"),
+            accounts.get("ORHUN").map(|a| a.name.as_str())
+        );
+        assert!(Account::new("carol", "").is_err());
+        assert!(accounts.get("carol").is_none());
+
+        accounts.record_game("alice bob", "orhun")?;
+        accounts.record_game("alice bob", "guest")?;
+        assert_eq!(
+            "1. alice bob (1231, 2W/0L)\n2. orhun (1184, 0W/1L)\n",
+            accounts.leaderboard()
+        );
+        assert_eq!(accounts, Accounts::load(&path)?);
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+//! Admin console.
+//!
+//! Operators connect to a separate listener (e.g. with `nc`), enter the admin token
+//! and control the server with the commands in [`HELP`].
+
+use crate::logger::Context;
+use crate::registry::{Registry, SHUTDOWN_MESSAGE};
+use crate::{log, secret_eq, Result};
+use std::error::Error as StdError;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Maximum number of the admin connections at a time.
+const MAX_CONNECTIONS: usize = 4;
+/// Time limit of entering the admin token.
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum number of the attempts to enter the admin token.
+const MAX_TOKEN_ATTEMPTS: u32 = 3;
+/// Maximum length of the admin token in bytes.
+const MAX_TOKEN_LENGTH: usize = 256;
+/// Delay after an invalid admin token.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+/// Time limit of sending a response.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Usage of the admin commands.
+pub const HELP: &str = "Commands:
+  players              List the connected players
+  matches              List the running matches
+  kick <player>        Disconnect a player
+  end <match>          End a running match
+  broadcast <message>  Send a message to all players
+  drain                Refuse new players and stop after the running matches
+  quit                 Close the console
+";
+
+/// Commands of the admin console.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Show the usage.
+    Help,
+    /// List the connected players.
+    Players,
+    /// List the running matches.
+    Matches,
+    /// Disconnect the player with the given ID.
+    Kick(u64),
+    /// End the match with the given ID.
+    End(u64),
+    /// Send the message to all players.
+    Broadcast(String),
+    /// Drain the server.
+    Drain,
+    /// Close the console.
+    Quit,
+}
+
+impl FromStr for Command {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let value = value.trim();
+        let (command, argument) = match value.find(' ') {
+            Some(index) => (&value[..index], value[index + 1..].trim()),
+            None => (value, ""),
+        };
+        let id = |name: &str| -> StdResult<u64, Self::Err> {
+            argument
+                .parse()
+                .map_err(|_| format!("Invalid {} ID: {:?}", name, argument).into())
+        };
+        match (command.to_lowercase().as_str(), argument) {
+            ("help", "") => Ok(Self::Help),
+            ("players", "") => Ok(Self::Players),
+            ("matches", "") => Ok(Self::Matches),
+            ("kick", _) => Ok(Self::Kick(id("player")?)),
+            ("end", _) => Ok(Self::End(id("match")?)),
+            ("broadcast", "") => Err("Message is missing".into()),
+            ("broadcast", message) => Ok(Self::Broadcast(message.to_string())),
+            ("drain", "") => Ok(Self::Drain),
+            ("quit", "") | ("exit", "") => Ok(Self::Quit),
+            _ => Err(format!("Unknown command: {} (type 'help' for the commands)", value).into()),
+        }
+    }
+}
+
+impl Command {
+    /// Executes the command and returns the response.
+    pub fn execute(&self, registry: &Registry) -> String {
+        match self {
+            Self::Help => HELP.to_string(),
+            Self::Players => {
+                let players = registry.players();
+                let mut s = format!("{} player(s) connected\n", players.len());
+                for player in players {
+                    let _ = writeln!(
+                        s,
+                        "{:>4}  {:<20} {:<22} {:<20} {}s",
+                        player.id,
+                        player.name,
+                        player.peer_addr,
+                        player.status,
+                        player.connected.as_secs()
+                    );
+                }
+                s
+            }
+            Self::Matches => {
+                let names = registry
+                    .players()
+                    .into_iter()
+                    .map(|player| (player.id, player.name))
+                    .collect::<Vec<(u64, String)>>();
+                let name = |id: &u64| {
+                    names
+                        .iter()
+                        .find(|(player_id, _)| player_id == id)
+                        .map(|(_, name)| format!("{} ({})", name, id))
+                        .unwrap_or_else(|| id.to_string())
+                };
+                let matches = registry.matches();
+                let mut s = format!("{} match(es) running\n", matches.len());
+                for running in matches {
+                    let _ = writeln!(
+                        s,
+                        "{:>4}  {}  {}s",
+                        running.id,
+                        running
+                            .players
+                            .iter()
+                            .map(name)
+                            .collect::<Vec<String>>()
+                            .join(" vs "),
+                        running.duration.as_secs()
+                    );
+                }
+                s
+            }
+            Self::Kick(id) => {
+                if registry.disconnect(*id, "\nYou are kicked by an admin.\n") {
+                    log!(Info, Context::player(*id), "Player is kicked by an admin.");
+                    format!("Player {} is kicked.\n", id)
+                } else {
+                    format!("Player {} is not connected.\n", id)
+                }
+            }
+            Self::End(id) => {
+                if registry.end(*id, "\nMatch is ended by an admin.\n") {
+                    log!(Info, Context::game(*id), "Match is ended by an admin.");
+                    format!("Match {} is ended.\n", id)
+                } else {
+                    format!("Match {} is not running.\n", id)
+                }
+            }
+            Self::Broadcast(message) => {
+                let count = registry.broadcast(&format!("\n[admin] {}\n", message));
+                log!(Info, "Admin broadcast: {}", message);
+                format!("Message is sent to {} connection(s).\n", count)
+            }
+            Self::Drain => {
+                registry.drain(SHUTDOWN_MESSAGE);
+                log!(Warn, "Server is draining.");
+                format!(
+                    "Server is draining, it stops after {} running match(es).\n",
+                    registry.matches().len()
+                )
+            }
+            Self::Quit => String::new(),
+        }
+    }
+}
+
+/// Reads the admin token from the stream before the given deadline.
+///
+/// Bytes are read one at a time so that the commands after the token stay in the stream.
+fn read_token(mut stream: &TcpStream, deadline: Instant) -> Result<String> {
+    let mut token = Vec::new();
+    let mut byte = [0];
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err("admin token is not entered in time".into());
+        }
+        stream.set_read_timeout(Some(deadline - now))?;
+        match stream.read(&mut byte) {
+            Ok(0) => break,
+            Ok(_) if byte[0] == b'\n' => break,
+            Ok(_) if token.len() < MAX_TOKEN_LENGTH => token.push(byte[0]),
+            Ok(_) => return Err("admin token is too long".into()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(String::from_utf8_lossy(&token).trim().to_string())
+}
+
+/// Handles an admin connection.
+///
+/// The token must be entered correctly in [`MAX_TOKEN_ATTEMPTS`] within [`TOKEN_TIMEOUT`].
+fn handle(stream: TcpStream, token: &str, registry: &Registry) -> Result<()> {
+    let peer_addr = stream.peer_addr()?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let mut writer = &stream;
+    let deadline = Instant::now() + TOKEN_TIMEOUT;
+    let mut attempts = 0;
+    loop {
+        writer.write_all(b"Admin token: ")?;
+        if secret_eq(&read_token(&stream, deadline)?, token) {
+            break;
+        }
+        attempts += 1;
+        log!(Warn, "Invalid admin token from {}", peer_addr);
+        if attempts == MAX_TOKEN_ATTEMPTS {
+            writer.write_all(b"Invalid token.\n")?;
+            return Ok(());
+        }
+        // Slow down guessing.
+        thread::sleep(RETRY_DELAY);
+        writer.write_all(b"Invalid token, try again.\n")?;
+    }
+    stream.set_read_timeout(None)?;
+    log!(Info, "Admin connected from {}", peer_addr);
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    writer.write_all(HELP.as_bytes())?;
+    loop {
+        writer.write_all(b"> ")?;
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match Command::from_str(&line) {
+            Ok(Command::Quit) => break,
+            Ok(command) => command.execute(registry),
+            Err(e) => format!("{}\n", e),
+        };
+        writer.write_all(response.as_bytes())?;
+    }
+    log!(Info, "Admin disconnected from {}", peer_addr);
+    Ok(())
+}
+
+/// Serves the admin console on the given address in a new thread.
+///
+/// Each connection is handled in its own thread, up to [`MAX_CONNECTIONS`] at a time.
+/// Returns the address that the console is listening on.
+pub fn serve(address: &str, token: &str, registry: Arc<Registry>) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    log!(Info, "Admin console is listening on {}", address);
+    let token = token.to_string();
+    let active = Arc::new(AtomicUsize::new(0));
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log!(Warn, Context::default(), "Admin console error: {}", e);
+                    continue;
+                }
+            };
+            if active.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+                let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+                let _ = stream.write_all(b"Too many admin connections.\n");
+                continue;
+            }
+            active.fetch_add(1, Ordering::SeqCst);
+            let (token, registry, active) = (
+                token.to_string(),
+                Arc::clone(&registry),
+                Arc::clone(&active),
+            );
+            thread::spawn(move || {
+                if let Err(e) = handle(stream, &token, &registry) {
+                    log!(Warn, Context::default(), "Admin console error: {}", e);
+                }
+                active.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    });
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_admin() -> Result<()> {
+        assert_eq!(Command::Players, "players".parse()?);
+        assert_eq!(Command::Kick(3), "KICK 3".parse()?);
+        assert_eq!(Command::End(12), " end  12 ".parse()?);
+        assert_eq!(
+            Command::Broadcast(String::from("Restarting in 5 minutes")),
+            "broadcast Restarting in 5 minutes".parse()?
+        );
+        assert!("kick alice".parse::<Command>().is_err());
+        assert!("broadcast".parse::<Command>().is_err());
+        assert!("shutdown".parse::<Command>().is_err());
+
+        let registry = Registry::default();
+        assert_eq!(
+            "0 player(s) connected\n",
+            Command::Players.execute(&registry)
+        );
+        assert_eq!(
+            "Player 3 is not connected.\n",
+            Command::Kick(3).execute(&registry)
+        );
+        assert_eq!(
+            "Match 12 is not running.\n",
+            Command::End(12).execute(&registry)
+        );
+        Command::Drain.execute(&registry);
+        assert!(registry.is_drained());
+        Ok(())
+    }
+
+    #[test]
+    fn test_serve() -> Result<()> {
+        let address = serve("127.0.0.1:0", "s3cret", Arc::new(Registry::default()))?;
+        let mut stream = TcpStream::connect(address)?;
+        stream.write_all(b"guess\nguess\nguess\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert_eq!(
+            "Admin token: Invalid token, try again.\n".repeat(2) + "Admin token: Invalid token.\n",
+            response
+        );
+
+        let mut stream = TcpStream::connect(address)?;
+        stream.write_all(b"s3cret\nmatches\nquit\n")?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+        assert!(response.starts_with(&format!("Admin token: {}> ", HELP)));
+        assert!(response.ends_with("> 0 match(es) running\n> "));
+        Ok(())
+    }
+}
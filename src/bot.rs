@@ -0,0 +1,339 @@
+//! Computer player.
+
+use crate::game::MAX_PLAYERS;
+use crate::grid::{column_name, CellState, Coordinate, Grid};
+use crate::map::Map;
+use crate::rules::Rules;
+use crate::Result;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+
+/// Prefix of the prompt for the player turn.
+const TURN_PROMPT: &str = "Your turn";
+
+/// Knowledge of a player about the opponent's grid.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tracking {
+    /// Width.
+    width: u8,
+    /// Height.
+    height: u8,
+    /// States of the cells in row-major order.
+    ///
+    /// Cells that are blocked by land or outside of the map are `None`.
+    cells: Vec<Option<CellState>>,
+}
+
+impl Tracking {
+    /// Constructs a new instance of [`Tracking`] from what the opponent can see on the grid.
+    pub fn from_grid(grid: &Grid) -> Self {
+        let cells = (1..=grid.height())
+            .flat_map(|y| (1..=grid.width()).map(move |x| Coordinate::from((x, y))))
+            .map(|coord| {
+                if grid.tile(coord).is_blocked() {
+                    None
+                } else {
+                    Some(grid.cell_state(coord))
+                }
+            })
+            .collect();
+        Self {
+            width: grid.width(),
+            height: grid.height(),
+            cells,
+        }
+    }
+
+    /// Parses the tracking grid that is shown by the server.
+    ///
+    /// See [`Grid::as_string`].
+    pub fn parse(value: &str) -> Result<Self> {
+        let mut lines = value.lines().skip_while(|line| line.trim().is_empty());
+        let header = lines.next().ok_or("grid is empty")?;
+        let width = header.split_whitespace().count();
+        if width == 0 || width > 255 {
+            return Err("invalid grid header".into());
+        }
+        let width = width as u8;
+        let row_width = header.chars().take_while(|c| *c == ' ').count();
+        let column_width = column_name(width).len() + 1;
+        let mut cells = Vec::new();
+        let mut height = 0_u8;
+        for line in lines {
+            let chars = line.chars().collect::<Vec<char>>();
+            let row = chars.iter().take(row_width).collect::<String>();
+            if row.trim().parse::<u8>().ok() != Some(height.wrapping_add(1)) {
+                break;
+            }
+            height += 1;
+            for x in 0..usize::from(width) {
+                let point = chars
+                    .get(row_width + x * column_width)
+                    .map(|c| c.to_string())
+                    .unwrap_or_default();
+                cells.push(CellState::from_point(&point));
+            }
+        }
+        if height == 0 {
+            return Err("grid has no rows".into());
+        }
+        Ok(Self {
+            width,
+            height,
+            cells,
+        })
+    }
+
+    /// Returns the width of the grid.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Returns the height of the grid.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Returns the state of the cell at the given coordinate.
+    ///
+    /// Returns `None` for the blocked cells.
+    pub fn state(&self, coordinate: Coordinate) -> Option<CellState> {
+        if (1..=self.width).contains(&coordinate.x) && (1..=self.height).contains(&coordinate.y) {
+            self.cells[usize::from(coordinate.y - 1) * usize::from(self.width)
+                + usize::from(coordinate.x - 1)]
+        } else {
+            None
+        }
+    }
+
+    /// Returns the adjacent coordinates of the given coordinate as `(dx, dy, coordinate)`.
+    fn neighbors(&self, coordinate: Coordinate) -> Vec<(i16, i16, Coordinate)> {
+        [(0, -1), (1, 0), (0, 1), (-1, 0)]
+            .iter()
+            .filter_map(|(dx, dy)| {
+                let (x, y) = (i16::from(coordinate.x) + dx, i16::from(coordinate.y) + dy);
+                if (1..=i16::from(self.width)).contains(&x)
+                    && (1..=i16::from(self.height)).contains(&y)
+                {
+                    Some((*dx, *dy, Coordinate::from((x as u8, y as u8))))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns a random coordinate among the given ones.
+fn pick(coordinates: &[Coordinate]) -> Option<Coordinate> {
+    if coordinates.is_empty() {
+        None
+    } else {
+        Some(coordinates[fastrand::usize(..coordinates.len())])
+    }
+}
+
+/// Returns the next coordinate to shoot.
+///
+/// Cells around the hit ships are targeted first, preferring the ones that are
+/// in line with other hits. Otherwise, cells that are detected by sonar and then
+/// the unknown cells on a checkerboard pattern are hunted.
+/// Returns `None` if there are no cells left to shoot.
+pub fn next_shot(tracking: &Tracking) -> Option<Coordinate> {
+    let coordinates = (1..=tracking.height)
+        .flat_map(|y| (1..=tracking.width).map(move |x| Coordinate::from((x, y))))
+        .collect::<Vec<Coordinate>>();
+    let is_open = |coord: Coordinate| {
+        matches!(
+            tracking.state(coord),
+            Some(CellState::Unknown) | Some(CellState::Detected)
+        )
+    };
+
+    // Target the cells around the hits.
+    let mut targets = Vec::new();
+    let mut best_score = 0;
+    for hit in coordinates
+        .iter()
+        .filter(|coord| tracking.state(**coord) == Some(CellState::Hit))
+    {
+        for (dx, dy, neighbor) in tracking.neighbors(*hit) {
+            if !is_open(neighbor) {
+                continue;
+            }
+            let in_line = tracking.neighbors(*hit).iter().any(|(ox, oy, coord)| {
+                *ox == -dx && *oy == -dy && tracking.state(*coord) == Some(CellState::Hit)
+            });
+            let score = if in_line { 2 } else { 1 };
+            if score > best_score {
+                best_score = score;
+                targets.clear();
+            }
+            if score == best_score && !targets.contains(&neighbor) {
+                targets.push(neighbor);
+            }
+        }
+    }
+    if let Some(target) = pick(&targets) {
+        return Some(target);
+    }
+
+    // Hunt for the ships.
+    let detected = coordinates
+        .iter()
+        .copied()
+        .filter(|coord| tracking.state(*coord) == Some(CellState::Detected))
+        .collect::<Vec<Coordinate>>();
+    let parity = coordinates
+        .iter()
+        .copied()
+        .filter(|coord| is_open(*coord) && (coord.x + coord.y) % 2 == 0)
+        .collect::<Vec<Coordinate>>();
+    let open = coordinates
+        .iter()
+        .copied()
+        .filter(|coord| is_open(*coord))
+        .collect::<Vec<Coordinate>>();
+    let clear = coordinates
+        .iter()
+        .copied()
+        .filter(|coord| tracking.state(*coord) == Some(CellState::Clear))
+        .collect::<Vec<Coordinate>>();
+    pick(&detected)
+        .or_else(|| pick(&parity))
+        .or_else(|| pick(&open))
+        .or_else(|| pick(&clear))
+}
+
+/// Plays the game on the server at the given address with the given name.
+///
+/// Registration and the lobby are skipped and the bot fires a shot whenever it is its turn.
+/// Returns the last message of the server which contains the result of the game.
+pub fn play(address: &str, name: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(address)?;
+    let mut output = String::new();
+    let mut buffer = [0; 4096];
+    loop {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(output);
+        }
+        output += &String::from_utf8_lossy(&buffer[..read]);
+        let last_line = output.lines().last().unwrap_or_default().to_string();
+        if last_line.ends_with("won.") || last_line.ends_with("won!") {
+            return Ok(last_line);
+        }
+        if output.ends_with("enter your name: ") {
+            stream.write_all(format!("{}\n", name).as_bytes())?;
+        } else if output.ends_with("press enter to play: ")
+            || output.ends_with("play as a guest): ")
+        {
+            stream.write_all(b"\n")?;
+        } else if last_line.starts_with(TURN_PROMPT) && output.ends_with(": ") {
+            // Upper grid is the tracking grid, lower grid is the own grid.
+            let grids = output.rsplitn(3, "\n\n").collect::<Vec<&str>>();
+            let tracking = grids
+                .get(1)
+                .ok_or("tracking grid is not found")
+                .map(|grid| Tracking::parse(grid))??;
+            let shot = next_shot(&tracking).ok_or("no cells left to shoot")?;
+            stream.write_all(format!("{}\n", shot).as_bytes())?;
+        } else {
+            continue;
+        }
+        output.clear();
+    }
+}
+
+/// Plays a game between two bots on the given map with the given rules.
+///
+/// Bots only fire and take turns until one of the grids is cleared.
+/// Returns the index of the winner and the number of the shots that the winner fired,
+/// or `None` if the bots run out of shots before a grid is cleared.
+pub fn selfplay(map: &Map, rules: &Rules) -> Option<(usize, u32)> {
+    let mut grids = (0..MAX_PLAYERS)
+        .map(|_| {
+            let mut grid = Grid::from_map(map.clone());
+            grid.place_random_ships(&rules.fleet);
+            grid
+        })
+        .collect::<Vec<Grid>>();
+    let mut shots = [0; MAX_PLAYERS];
+    let mut turn = 0;
+    loop {
+        let opponent = &mut grids[MAX_PLAYERS - (turn + 1)];
+        opponent.shoot(next_shot(&Tracking::from_grid(opponent))?);
+        shots[turn] += 1;
+        if opponent.is_cleared() {
+            return Some((turn, shots[turn]));
+        }
+        turn = MAX_PLAYERS - (turn + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::rules::Fleet;
+    use crate::ship::{Orientation, Ship, ShipType};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_bot() -> Result<()> {
+        let mut grid = Grid::from_map(Map::from_str("....#\n.....\n.... ")?);
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::East),
+            vec![Coordinate::from((2, 2)), Coordinate::from((3, 2))]
+        )));
+        grid.shoot(Coordinate::from((1, 1)));
+        grid.shoot(Coordinate::from((2, 2)));
+        let tracking = Tracking::from_grid(&grid);
+        assert_eq!(tracking, Tracking::parse(&grid.as_string(false)?)?);
+        assert_eq!(None, tracking.state(Coordinate::from((5, 1))));
+        assert_eq!(None, tracking.state(Coordinate::from((5, 3))));
+        assert_eq!(
+            Some(CellState::Miss),
+            tracking.state(Coordinate::from((1, 1)))
+        );
+        for _ in 0..10 {
+            let shot = next_shot(&tracking).expect("no shot");
+            assert!(tracking
+                .neighbors(Coordinate::from((2, 2)))
+                .iter()
+                .any(|(_, _, coord)| *coord == shot));
+        }
+        grid.shoot(Coordinate::from((3, 2)));
+        let tracking = Tracking::from_grid(&grid);
+        for _ in 0..10 {
+            let shot = next_shot(&tracking).expect("no shot");
+            assert_eq!(0, (shot.x + shot.y) % 2);
+        }
+
+        // Bot clears the grid eventually.
+        let mut grid = Grid::new(12, 12);
+        grid.place_random_ships(&Fleet::Classic);
+        let mut shots = 0;
+        while !grid.is_cleared() {
+            let shot = next_shot(&Tracking::from_grid(&grid)).expect("no shot");
+            assert_eq!(CellState::Unknown, grid.cell_state(shot));
+            grid.shoot(shot);
+            shots += 1;
+        }
+        assert!(shots < 144);
+        let (winner, shots) = selfplay(&Map::new(8, 8), &Rules::default()).expect("no winner");
+        assert!(winner < MAX_PLAYERS);
+        assert!(shots > 0 && shots <= 64);
+
+        // Wide grids are parsed.
+        let mut grid = Grid::new(30, 10);
+        grid.shoot(Coordinate::from((28, 10)));
+        let tracking = Tracking::parse(&grid.as_string(false)?)?;
+        assert_eq!((30, 10), (tracking.width(), tracking.height()));
+        assert_eq!(
+            Some(CellState::Miss),
+            tracking.state(Coordinate::from((28, 10)))
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,414 @@
+//! Command-line interface.
+
+use crate::config::{parse_number, Config, CONFIG_KEYS, DEFAULT_SOCKET_ADDR};
+use crate::Result;
+use std::path::PathBuf;
+
+/// Default name of the bot.
+const DEFAULT_BOT_NAME: &str = "Bot";
+/// Default number of the games to self-play.
+const DEFAULT_GAMES: u32 = 100;
+/// Option for the configuration file.
+const CONFIG_OPTION: &str = "config";
+
+/// Options that take a value and the subcommands that accept them.
+const COMMAND_OPTIONS: &[(&str, &str)] = &[("name", "bot"), ("games", "selfplay")];
+
+/// Options that do not take a value and the subcommands that accept them.
+const COMMAND_SWITCHES: &[(&str, &str)] = &[
+    ("headless", "replay"),
+    ("csv", "leaderboard"),
+    ("heatmap-csv", "leaderboard"),
+];
+
+/// Usage of the command-line interface.
+pub const HELP: &str = concat!(
+    "Usage: ",
+    env!("CARGO_PKG_NAME"),
+    " [COMMAND] [OPTIONS]
+
+Commands:
+  serve                       Serve the game (default)
+  replay <FILE>               Step through a recorded game
+  leaderboard <DIRECTORY>     Show the statistics of the recorded games
+  bot [ADDRESS]               Play on a server as a bot (default: 127.0.0.1:1234)
+  selfplay                    Play bots against each other and show the results
+  check-config                Validate the configuration and exit
+
+Options:
+      --config <FILE>         Path of the configuration file
+      --socket <ADDRESS>      TCP socket address (default: 127.0.0.1:1234)
+      --grid-width <WIDTH>    Width of the game grid (default: 10, maximum: 255)
+      --grid-height <HEIGHT>  Height of the game grid (default: 10, maximum: 255)
+      --map <FILE>            Path of the map file (overrides the grid size)
+      --fleet <FLEET>         Fleet of the players, orhun or classic (default: orhun)
+      --ships <FILE>          Path of the custom ship definitions file (overrides the fleet)
+      --mode <MODE>           Game mode, standard, advanced or mobile (default: standard)
+      --turn-timeout <SECS>   Time limit of a turn, 0 for no limit (default: 0)
+      --countdown <SECS>      Length of the countdown before the game (default: 3)
+      --shutdown-timeout <SECS>
+                              Time limit of the running matches on shutdown (default: 30)
+      --banner <TEXT>         Banner that is shown to the new connections
+      --log-level <LEVEL>     Log level, error, warn, info or debug (default: info)
+      --log-format <FORMAT>   Format of the log lines, text or json (default: text)
+      --log-boards <BOOL>     Log the grids of the players at the start of the games (default: false)
+      --records <DIRECTORY>   Directory for saving the records of the finished games
+      --admin-password <PASS> Password for watching the games in omniscient mode
+      --accounts <FILE>       Path of the player accounts file
+      --metrics <ADDRESS>     Address of the metrics endpoint (disabled by default)
+      --admin-socket <ADDRESS>
+                              Address of the admin console (disabled by default)
+      --admin-token <TOKEN>   Token for the admin console
+      --max-connections <NUMBER>
+                              Maximum number of the connections, 0 for no limit (default: 256)
+      --max-connections-per-ip <NUMBER>
+                              Maximum number of the connections from an address (default: 8)
+      --handshake-timeout <SECS>
+                              Time limit of entering the name and password (default: 30)
+      --rate-limit <NUMBER>   Maximum number of the lines per second from a connection (default: 20)
+      --bans <FILE>           Path of the ban list file
+      --workers <NUMBER>      Number of the worker threads for the lobby (default: 8)
+      --headless              Print the end of the game without the controls (replay)
+      --csv                   Print the player statistics as CSV (leaderboard)
+      --heatmap-csv           Print the heat map as CSV (leaderboard)
+      --name <NAME>           Name of the bot (bot, default: Bot)
+      --games <NUMBER>        Number of the games to play (selfplay, default: 100)
+  -h, --help                  Print help
+  -V, --version               Print version
+
+Settings are read from the configuration file, then the environment variables
+(e.g. BATTLESHIP_GRID_WIDTH for --grid-width) and then the flags. Later sources
+override the earlier ones.
+"
+);
+
+/// Returns the name of the environment variable for the given configuration key.
+pub fn env_name(key: &str) -> String {
+    format!("{}_{}", env!("CARGO_PKG_NAME"), key).to_uppercase()
+}
+
+/// Format of the leaderboard output.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LeaderboardFormat {
+    /// Leaderboard and the heat map as a table.
+    Table,
+    /// Statistics of the players as CSV.
+    Csv,
+    /// Heat map as CSV.
+    HeatMapCsv,
+}
+
+/// Subcommands of the command-line interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Subcommand {
+    /// Serve the game.
+    Serve,
+    /// Replay the recorded game at the given path.
+    Replay {
+        /// Path of the record.
+        path: PathBuf,
+        /// Whether if the controls are skipped.
+        headless: bool,
+    },
+    /// Show the statistics of the records in the given directory.
+    Leaderboard {
+        /// Directory of the records.
+        directory: PathBuf,
+        /// Output format.
+        format: LeaderboardFormat,
+    },
+    /// Play on the server at the given address.
+    Bot {
+        /// Address of the server.
+        address: String,
+        /// Name of the bot.
+        name: String,
+    },
+    /// Play the given number of games between two bots.
+    Selfplay {
+        /// Number of the games.
+        games: u32,
+    },
+    /// Validate the configuration.
+    CheckConfig,
+    /// Print the usage.
+    Help,
+    /// Print the version.
+    Version,
+}
+
+/// Parsed command-line arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cli {
+    /// Subcommand to run.
+    pub subcommand: Subcommand,
+    /// Configuration of the server.
+    pub config: Config,
+}
+
+impl Cli {
+    /// Parses the given arguments (without the program name).
+    ///
+    /// Arguments after `--` are not parsed as options.
+    ///
+    /// Configuration is loaded from the file that is given with `--config` (or
+    /// `BATTLESHIP_CONFIG`) and then overridden by the environment variables that are
+    /// read with the given function and then by the flags.
+    pub fn parse<F: Fn(&str) -> Option<String>>(args: &[String], env: F) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut settings = Vec::new();
+        let mut options = Vec::new();
+        let mut switches = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                return Ok(Self {
+                    subcommand: Subcommand::Help,
+                    config: Config::default(),
+                });
+            } else if arg == "--" {
+                // The rest of the arguments are positional, e.g. file names that start with `-`.
+                positional.extend(args.by_ref().map(String::as_str));
+            } else if arg == "-V" || arg == "--version" {
+                return Ok(Self {
+                    subcommand: Subcommand::Version,
+                    config: Config::default(),
+                });
+            } else if let Some(arg) = arg.strip_prefix("--") {
+                let mut parts = arg.splitn(2, '=');
+                let option = parts.next().unwrap_or_default();
+                let inline_value = parts.next();
+                if COMMAND_SWITCHES.iter().any(|(name, _)| *name == option) {
+                    if inline_value.is_some() {
+                        return Err(format!("--{} does not take a value", option).into());
+                    }
+                    switches.push(option);
+                    continue;
+                }
+                let key = option.replace('-', "_");
+                let is_setting = option == CONFIG_OPTION || CONFIG_KEYS.contains(&key.as_str());
+                if !is_setting && !COMMAND_OPTIONS.iter().any(|(name, _)| *name == option) {
+                    return Err(format!("Unknown option: --{}", option).into());
+                }
+                let value = match inline_value {
+                    Some(value) => value,
+                    None => args
+                        .next()
+                        .ok_or_else(|| format!("--{} needs a value", option))?,
+                };
+                if is_setting {
+                    settings.push((key, option, value));
+                } else {
+                    options.push((option, value));
+                }
+            } else if arg.starts_with('-') && arg.len() > 1 {
+                return Err(format!("Unknown option: {}", arg).into());
+            } else {
+                positional.push(arg.as_str());
+            }
+        }
+
+        // Load the configuration file.
+        let config_path = settings
+            .iter()
+            .rev()
+            .find(|(key, _, _)| key == CONFIG_OPTION)
+            .map(|(_, _, value)| value.to_string())
+            .or_else(|| env(&env_name(CONFIG_OPTION)));
+        let mut config = match config_path {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        // Override with the environment variables and then the flags.
+        for key in CONFIG_KEYS {
+            let name = env_name(key);
+            if let Some(value) = env(&name) {
+                config
+                    .set(key, &value)
+                    .map_err(|e| format!("{}: {}", name, e))?;
+            }
+        }
+        for (key, option, value) in settings.iter().filter(|(key, _, _)| key != CONFIG_OPTION) {
+            config
+                .set(key, value)
+                .map_err(|e| format!("--{}: {}", option, e))?;
+        }
+
+        let command = positional.first().copied().unwrap_or("serve");
+        for option in options
+            .iter()
+            .map(|(option, _)| option)
+            .chain(switches.iter())
+        {
+            if let Some((_, subcommand)) = COMMAND_OPTIONS
+                .iter()
+                .chain(COMMAND_SWITCHES)
+                .find(|(name, subcommand)| name == option && *subcommand != command)
+            {
+                return Err(format!("--{} can only be used with {}", option, subcommand).into());
+            }
+        }
+        let option = |name: &str| {
+            options
+                .iter()
+                .rev()
+                .find(|(option, _)| *option == name)
+                .map(|(_, value)| *value)
+        };
+        let arguments = positional.get(1..).unwrap_or_default();
+        let subcommand = match (command, arguments) {
+            ("serve", []) => Subcommand::Serve,
+            ("replay", [path]) => Subcommand::Replay {
+                path: PathBuf::from(path),
+                headless: switches.contains(&"headless"),
+            },
+            ("leaderboard", [directory]) => Subcommand::Leaderboard {
+                directory: PathBuf::from(directory),
+                format: match (switches.contains(&"csv"), switches.contains(&"heatmap-csv")) {
+                    (false, false) => LeaderboardFormat::Table,
+                    (true, false) => LeaderboardFormat::Csv,
+                    (false, true) => LeaderboardFormat::HeatMapCsv,
+                    (true, true) => {
+                        return Err("--csv and --heatmap-csv cannot be used together".into())
+                    }
+                },
+            },
+            ("bot", []) | ("bot", [_]) => Subcommand::Bot {
+                address: arguments
+                    .first()
+                    .copied()
+                    .unwrap_or(DEFAULT_SOCKET_ADDR)
+                    .to_string(),
+                name: option("name").unwrap_or(DEFAULT_BOT_NAME).to_string(),
+            },
+            ("selfplay", []) => Subcommand::Selfplay {
+                games: match option("games") {
+                    Some(games) => parse_number(games).map_err(|e| format!("--games: {}", e))?,
+                    None => DEFAULT_GAMES,
+                },
+            },
+            ("check-config", []) => Subcommand::CheckConfig,
+            ("serve", _)
+            | ("replay", _)
+            | ("leaderboard", _)
+            | ("bot", _)
+            | ("selfplay", _)
+            | ("check-config", _) => {
+                return Err(format!(
+                    "Invalid arguments for {} (see --help for the usage)",
+                    command
+                )
+                .into())
+            }
+            _ => return Err(format!("Unknown command: {} (see --help)", command).into()),
+        };
+        Ok(Self { subcommand, config })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::rules::Mode;
+    use std::env;
+    use std::fs;
+
+    /// Parses the given arguments with the given environment variables.
+    fn parse(args: &str, env: &[(&str, &str)]) -> Result<Cli> {
+        let args = args
+            .split_whitespace()
+            .map(String::from)
+            .collect::<Vec<String>>();
+        Cli::parse(&args, |name| {
+            env.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_cli() -> Result<()> {
+        assert_eq!("BATTLESHIP_GRID_WIDTH", env_name("grid_width"));
+        let cli = parse("", &[])?;
+        assert_eq!(Subcommand::Serve, cli.subcommand);
+        assert_eq!(Config::default(), cli.config);
+
+        let cli = parse(
+            "serve --grid-width 12 --mode=advanced --records records",
+            &[
+                ("BATTLESHIP_GRID_WIDTH", "8"),
+                ("BATTLESHIP_GRID_HEIGHT", "6"),
+            ],
+        )?;
+        assert_eq!((12, 6), (cli.config.grid_width, cli.config.grid_height));
+        assert_eq!(Mode::Advanced, cli.config.mode);
+        assert_eq!(Some(PathBuf::from("records")), cli.config.records_dir);
+        assert_eq!(Map::new(12, 6), cli.config.map()?);
+
+        let path = env::temp_dir().join(format!("battleship-config-{}", fastrand::u64(..)));
+        fs::write(&path, "grid_width = 20\ngrid_height = 20\nmode = mobile\n")?;
+        let cli = parse(
+            &format!("--grid-width=12 --config {}", path.display()),
+            &[
+                ("BATTLESHIP_GRID_HEIGHT", "6"),
+                ("BATTLESHIP_GRID_WIDTH", "8"),
+            ],
+        )?;
+        assert_eq!((12, 6), (cli.config.grid_width, cli.config.grid_height));
+        assert_eq!(Mode::Mobile, cli.config.mode);
+        fs::remove_file(&path)?;
+        assert!(parse(&format!("--config {}", path.display()), &[]).is_err());
+
+        assert_eq!(
+            Subcommand::Replay {
+                path: PathBuf::from("game.record"),
+                headless: true
+            },
+            parse("replay --headless game.record", &[])?.subcommand
+        );
+        assert_eq!(
+            Subcommand::Bot {
+                address: String::from("10.0.0.1:1234"),
+                name: String::from("Robo")
+            },
+            parse("bot 10.0.0.1:1234 --name Robo", &[])?.subcommand
+        );
+        assert_eq!(
+            Subcommand::Selfplay { games: 5 },
+            parse("selfplay --games=5 --fleet classic", &[])?.subcommand
+        );
+        assert_eq!(Subcommand::Help, parse("replay --help", &[])?.subcommand);
+        assert_eq!(
+            Subcommand::Replay {
+                path: PathBuf::from("--help"),
+                headless: true
+            },
+            parse("replay --headless -- --help", &[])?.subcommand
+        );
+
+        for (args, env) in &[
+            ("", ("BATTLESHIP_GRID_WIDTH", "abc")),
+            ("--grid-height 0", ("", "")),
+            ("--mode fast", ("", "")),
+            ("--grid-width", ("", "")),
+            ("--colour", ("", "")),
+            ("fly", ("", "")),
+            ("replay", ("", "")),
+            ("serve --headless", ("", "")),
+            ("leaderboard records --csv=yes", ("", "")),
+            ("selfplay --games many", ("", "")),
+        ] {
+            assert!(parse(args, &[*env]).is_err(), "{} is parsed", args);
+        }
+        assert_eq!(
+            Some(String::from(
+                "BATTLESHIP_GRID_WIDTH: Invalid number: abc (invalid digit found in string)"
+            )),
+            parse("", &[("BATTLESHIP_GRID_WIDTH", "abc")])
+                .err()
+                .map(|e| e.to_string())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,508 @@
+//! Server configuration.
+
+use crate::account::Accounts;
+use crate::limits::BanList;
+use crate::logger::{Format, Level};
+use crate::map::Map;
+use crate::rules::{Fleet, Mode, Rules};
+use crate::shape::ShipDefinition;
+use crate::{Result, BANNER};
+use std::error::Error as StdError;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Default TCP socket address to serve the game.
+pub const DEFAULT_SOCKET_ADDR: &str = "127.0.0.1:1234";
+/// Default width and height of the grid.
+const DEFAULT_GRID_SIZE: u8 = 10;
+/// Default length of the countdown before the game in seconds.
+const DEFAULT_COUNTDOWN: u64 = 3;
+/// Default time limit of the running matches on shutdown in seconds.
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+/// Default maximum number of the connections.
+const DEFAULT_MAX_CONNECTIONS: usize = 256;
+/// Default maximum number of the connections from an IP address.
+const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 8;
+/// Default time limit of entering the name and logging in, in seconds.
+const DEFAULT_HANDSHAKE_TIMEOUT: u64 = 30;
+/// Default maximum number of the lines that are read from a connection per second.
+const DEFAULT_RATE_LIMIT: u32 = 20;
+/// Default number of the worker threads that handle the connections in the lobby.
+const DEFAULT_WORKERS: usize = 8;
+/// The prefix of the comment lines in a configuration file.
+const COMMENT_PREFIX: char = ';';
+
+/// Keys of the configuration.
+///
+/// Each key can also be set with an environment variable (e.g. `BATTLESHIP_GRID_WIDTH`)
+/// and a flag (e.g. `--grid-width`).
+pub const CONFIG_KEYS: &[&str] = &[
+    "socket",
+    "grid_width",
+    "grid_height",
+    "map",
+    "fleet",
+    "ships",
+    "mode",
+    "turn_timeout",
+    "countdown",
+    "shutdown_timeout",
+    "banner",
+    "log_level",
+    "log_format",
+    "log_boards",
+    "records",
+    "admin_password",
+    "accounts",
+    "metrics",
+    "admin_socket",
+    "admin_token",
+    "max_connections",
+    "max_connections_per_ip",
+    "handshake_timeout",
+    "rate_limit",
+    "bans",
+    "workers",
+];
+
+/// Parses the value as a number.
+pub(crate) fn parse_number<T: FromStr<Err = std::num::ParseIntError>>(value: &str) -> Result<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid number: {} ({})", value, e).into())
+}
+
+/// Parses the value as a duration in seconds, 0 for no limit.
+fn parse_timeout(value: &str) -> Result<Option<Duration>> {
+    match parse_number(value)? {
+        0 => Ok(None),
+        seconds => Ok(Some(Duration::from_secs(seconds))),
+    }
+}
+
+/// Parses the value as a boolean.
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" => Ok(true),
+        "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("Invalid boolean: {} (expected true or false)", value).into()),
+    }
+}
+
+/// Parses the value as a grid size.
+fn parse_grid_size(value: &str) -> Result<u8> {
+    match parse_number(value)? {
+        0 => Err("Grid size must be greater than zero".into()),
+        size => Ok(size),
+    }
+}
+
+/// Parses the value as a number of worker threads.
+fn parse_workers(value: &str) -> Result<usize> {
+    match parse_number(value)? {
+        0 => Err("Number of workers must be greater than zero".into()),
+        workers => Ok(workers),
+    }
+}
+
+/// Configuration of the server and the game rules.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// TCP socket address.
+    pub socket_addr: String,
+    /// Width of the grid.
+    pub grid_width: u8,
+    /// Height of the grid.
+    pub grid_height: u8,
+    /// Path of the map file.
+    pub map: Option<PathBuf>,
+    /// Fleet of the players.
+    pub fleet: Fleet,
+    /// Path of the custom ship definitions file.
+    pub ships: Option<PathBuf>,
+    /// Game mode.
+    pub mode: Mode,
+    /// Time limit of a turn.
+    pub turn_timeout: Option<Duration>,
+    /// Length of the countdown before the game in seconds.
+    pub countdown: u64,
+    /// Time limit of the running matches on shutdown.
+    pub shutdown_timeout: Duration,
+    /// Banner that is shown to the new connections.
+    pub banner: String,
+    /// Log level.
+    pub log_level: Level,
+    /// Format of the log lines.
+    pub log_format: Format,
+    /// Whether if the grids of the players are logged at the start of the games.
+    pub log_boards: bool,
+    /// Directory of the game records.
+    pub records_dir: Option<PathBuf>,
+    /// Password for the omniscient spectators.
+    pub admin_password: Option<String>,
+    /// Path of the accounts file.
+    pub accounts: Option<PathBuf>,
+    /// Address of the metrics endpoint.
+    pub metrics_addr: Option<String>,
+    /// Address of the admin console.
+    pub admin_socket: Option<String>,
+    /// Token for the admin console.
+    pub admin_token: Option<String>,
+    /// Maximum number of the connections, 0 for no limit.
+    pub max_connections: usize,
+    /// Maximum number of the connections from an IP address, 0 for no limit.
+    pub max_connections_per_ip: usize,
+    /// Time limit of entering the name and logging in.
+    pub handshake_timeout: Option<Duration>,
+    /// Maximum number of the lines that are read from a connection per second, 0 for no limit.
+    pub rate_limit: u32,
+    /// Path of the ban list file.
+    pub bans: Option<PathBuf>,
+    /// Number of the worker threads that handle the connections in the lobby.
+    pub workers: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            socket_addr: DEFAULT_SOCKET_ADDR.to_string(),
+            grid_width: DEFAULT_GRID_SIZE,
+            grid_height: DEFAULT_GRID_SIZE,
+            map: None,
+            fleet: Fleet::default(),
+            ships: None,
+            mode: Mode::default(),
+            turn_timeout: None,
+            countdown: DEFAULT_COUNTDOWN,
+            shutdown_timeout: Duration::from_secs(DEFAULT_SHUTDOWN_TIMEOUT),
+            banner: BANNER.to_string(),
+            log_level: Level::default(),
+            log_format: Format::default(),
+            log_boards: false,
+            records_dir: None,
+            admin_password: None,
+            accounts: None,
+            metrics_addr: None,
+            admin_socket: None,
+            admin_token: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            max_connections_per_ip: DEFAULT_MAX_CONNECTIONS_PER_IP,
+            handshake_timeout: Some(Duration::from_secs(DEFAULT_HANDSHAKE_TIMEOUT)),
+            rate_limit: DEFAULT_RATE_LIMIT,
+            bans: None,
+            workers: DEFAULT_WORKERS,
+        }
+    }
+}
+
+/// Parses the configuration from the contents of a configuration file.
+///
+/// Each line is in the form of `key = value` and lines starting with `;` are ignored.
+/// `banner` can be repeated for multiple lines.
+impl FromStr for Config {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let mut config = Self::default();
+        let mut banner = Vec::new();
+        for (i, line) in value.lines().enumerate() {
+            let line = line.trim_end();
+            if line.trim().is_empty() || line.trim_start().starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value),
+                _ => return Err(format!("line {}: invalid line: {}", i + 1, line).into()),
+            };
+            // Leading spaces are significant in the banner.
+            if key == "banner" {
+                banner.push(value.strip_prefix(' ').unwrap_or(value));
+                continue;
+            }
+            config
+                .set(key, value.trim())
+                .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        }
+        if !banner.is_empty() {
+            config.banner = banner.join("\n");
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(&path)?.parse().map_err(|e| {
+            format!(
+                "Failed to load configuration from {}: {}",
+                path.as_ref().display(),
+                e
+            )
+            .into()
+        })
+    }
+
+    /// Sets the value of the given key.
+    ///
+    /// See [`CONFIG_KEYS`] for the available keys.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "socket" => self.socket_addr = value.to_string(),
+            "grid_width" => self.grid_width = parse_grid_size(value)?,
+            "grid_height" => self.grid_height = parse_grid_size(value)?,
+            "map" => self.map = Some(PathBuf::from(value)),
+            "fleet" => self.fleet = value.parse()?,
+            "ships" => self.ships = Some(PathBuf::from(value)),
+            "mode" => self.mode = value.parse()?,
+            "turn_timeout" => self.turn_timeout = parse_timeout(value)?,
+            "countdown" => self.countdown = parse_number(value)?,
+            "shutdown_timeout" => self.shutdown_timeout = Duration::from_secs(parse_number(value)?),
+            "banner" => self.banner = value.to_string(),
+            "log_level" => self.log_level = value.parse()?,
+            "log_format" => self.log_format = value.parse()?,
+            "log_boards" => self.log_boards = parse_bool(value)?,
+            "records" => self.records_dir = Some(PathBuf::from(value)),
+            "admin_password" => self.admin_password = Some(value.to_string()),
+            "accounts" => self.accounts = Some(PathBuf::from(value)),
+            "metrics" => self.metrics_addr = Some(value.to_string()),
+            "admin_socket" => self.admin_socket = Some(value.to_string()),
+            "admin_token" => self.admin_token = Some(value.to_string()),
+            "max_connections" => self.max_connections = parse_number(value)?,
+            "max_connections_per_ip" => self.max_connections_per_ip = parse_number(value)?,
+            "handshake_timeout" => self.handshake_timeout = parse_timeout(value)?,
+            "rate_limit" => self.rate_limit = parse_number(value)?,
+            "bans" => self.bans = Some(PathBuf::from(value)),
+            "workers" => self.workers = parse_workers(value)?,
+            _ => return Err(format!("Unknown key: {}", key).into()),
+        }
+        Ok(())
+    }
+
+    /// Returns the value of the given key as string.
+    ///
+    /// Admin password and token are masked. See [`CONFIG_KEYS`] for the available keys.
+    pub fn value(&self, key: &str) -> String {
+        let secret = |secret: &Option<String>| {
+            secret
+                .as_ref()
+                .map(|_| String::from("***"))
+                .unwrap_or_else(|| String::from("-"))
+        };
+        let timeout = |timeout: Option<Duration>| {
+            timeout
+                .map(|timeout| timeout.as_secs())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| String::from("-"))
+        };
+        match key {
+            "socket" => self.socket_addr.to_string(),
+            "grid_width" => self.grid_width.to_string(),
+            "grid_height" => self.grid_height.to_string(),
+            "map" => path(&self.map),
+            "fleet" => self.fleet.to_string(),
+            "ships" => path(&self.ships),
+            "mode" => self.mode.to_string(),
+            "turn_timeout" => timeout(self.turn_timeout),
+            "countdown" => self.countdown.to_string(),
+            "shutdown_timeout" => self.shutdown_timeout.as_secs().to_string(),
+            "banner" => format!("{:?}", self.banner),
+            "log_level" => self.log_level.to_string(),
+            "log_format" => self.log_format.to_string(),
+            "log_boards" => self.log_boards.to_string(),
+            "records" => path(&self.records_dir),
+            "admin_password" => secret(&self.admin_password),
+            "accounts" => path(&self.accounts),
+            "metrics" => self
+                .metrics_addr
+                .clone()
+                .unwrap_or_else(|| String::from("-")),
+            "admin_socket" => self
+                .admin_socket
+                .clone()
+                .unwrap_or_else(|| String::from("-")),
+            "admin_token" => secret(&self.admin_token),
+            "max_connections" => self.max_connections.to_string(),
+            "max_connections_per_ip" => self.max_connections_per_ip.to_string(),
+            "handshake_timeout" => timeout(self.handshake_timeout),
+            "rate_limit" => self.rate_limit.to_string(),
+            "bans" => path(&self.bans),
+            "workers" => self.workers.to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Returns the map of the game.
+    ///
+    /// The map file is loaded if it is given, a rectangular map is used otherwise.
+    pub fn map(&self) -> Result<Map> {
+        match &self.map {
+            Some(path) => Map::load(path),
+            None => Ok(Map::new(self.grid_width, self.grid_height)),
+        }
+    }
+
+    /// Returns the rules of the game.
+    ///
+    /// The custom ship definitions are loaded if they are given.
+    pub fn rules(&self) -> Result<Rules> {
+        let fleet = match &self.ships {
+            Some(path) => Fleet::Custom(ShipDefinition::load_all(path)?),
+            None => self.fleet.clone(),
+        };
+        Ok(Rules {
+            fleet,
+            mode: self.mode,
+        })
+    }
+
+    /// Returns the ban list, which is empty if the ban list file is not given.
+    pub fn bans(&self) -> Result<BanList> {
+        match &self.bans {
+            Some(path) => BanList::load(path),
+            None => Ok(BanList::default()),
+        }
+    }
+
+    /// Returns the player accounts if the accounts file is given.
+    pub fn accounts(&self) -> Result<Option<Accounts>> {
+        self.accounts.as_ref().map(Accounts::load).transpose()
+    }
+}
+
+/// Configuration along with the map and the rules that are loaded from it.
+///
+/// Settings are shared between the connections and replaced as a whole on reload,
+/// so the running games keep the settings that they are started with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Settings {
+    /// Configuration.
+    pub config: Config,
+    /// Map of the game.
+    pub map: Map,
+    /// Rules of the game.
+    pub rules: Rules,
+    /// Banned addresses.
+    pub bans: BanList,
+}
+
+impl Settings {
+    /// Loads the map and the rules of the given configuration.
+    pub fn load(config: Config) -> Result<Self> {
+        let map = config.map()?;
+        if map.width() == 0 || map.height() == 0 {
+            return Err("Invalid grid dimensions".into());
+        }
+        if config.admin_socket.is_some()
+            && config
+                .admin_token
+                .as_ref()
+                .map_or(true, |token| token.is_empty())
+        {
+            return Err("admin_token is required for the admin console".into());
+        }
+        Ok(Self {
+            rules: config.rules()?,
+            bans: config.bans()?,
+            map,
+            config,
+        })
+    }
+
+    /// Returns the descriptions of the changes from these settings to the given ones.
+    ///
+    /// Changes in the contents of the map, ship definition and ban list files are included.
+    pub fn changes(&self, other: &Self) -> Vec<String> {
+        let mut changes = CONFIG_KEYS
+            .iter()
+            .filter_map(|key| {
+                let (old, new) = (self.config.value(key), other.config.value(key));
+                if old == new {
+                    None
+                } else {
+                    Some(format!("{}: {} -> {}", key, old, new))
+                }
+            })
+            .collect::<Vec<String>>();
+        if self.config.map.is_some() && self.config.map == other.config.map && self.map != other.map
+        {
+            changes.push(String::from("map: file is changed"));
+        }
+        if self.config.ships.is_some()
+            && self.config.ships == other.config.ships
+            && self.rules.fleet != other.rules.fleet
+        {
+            changes.push(String::from("ships: file is changed"));
+        }
+        if self.config.bans.is_some()
+            && self.config.bans == other.config.bans
+            && self.bans != other.bans
+        {
+            changes.push(String::from("bans: file is changed"));
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() -> Result<()> {
+        let config = Config::from_str(
+            "; Example configuration
+            socket = 0.0.0.0:1234
+            ; Board settings
+            grid_width = 12
+            fleet = classic
+            turn_timeout = 30
+            countdown = 0
+            shutdown_timeout = 5
+            banner =  ~~ Harbor ~~
+            banner = Welcome!
+            log_level = warn
+            log_boards = yes",
+        )?;
+        assert_eq!("0.0.0.0:1234", config.socket_addr);
+        assert_eq!((12, 10), (config.grid_width, config.grid_height));
+        assert_eq!(Fleet::Classic, config.fleet);
+        assert_eq!(Some(Duration::from_secs(30)), config.turn_timeout);
+        assert_eq!(0, config.countdown);
+        assert_eq!(Duration::from_secs(5), config.shutdown_timeout);
+        assert_eq!(" ~~ Harbor ~~\nWelcome!", config.banner);
+        assert_eq!(Level::Warn, config.log_level);
+        assert!(config.log_boards);
+        assert_eq!(Mode::Standard, config.mode);
+
+        let settings = Settings::load(config.clone())?;
+        let mut other = config;
+        other.set("mode", "mobile")?;
+        other.set("admin_password", "hunter2")?;
+        assert_eq!(
+            vec!["mode: standard -> mobile", "admin_password: - -> ***"],
+            settings.changes(&Settings::load(other)?)
+        );
+        assert!(settings.changes(&settings).is_empty());
+
+        for invalid in &[
+            "grid_width = 300",
+            "colour = red",
+            "mode",
+            "turn_timeout = -1",
+            "workers = 0",
+        ] {
+            assert!(Config::from_str(invalid).is_err(), "{} is parsed", invalid);
+        }
+        Ok(())
+    }
+}
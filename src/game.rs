@@ -1,24 +1,186 @@
 //! Main game.
 
+use crate::grid::CellState;
 use crate::grid::Coordinate;
 use crate::grid::Grid;
+use crate::logger::Context;
+use crate::map::Tile;
+use crate::metrics::METRICS;
 use crate::player::Player;
-use crate::ship::Ship;
-use crate::Result;
-use std::convert::TryFrom;
+use crate::record::{self, PlayerRecord, Record, Turn};
+use crate::registry::{Registry, Status};
+use crate::rules::{Mode, Rules};
+use crate::ship::Orientation;
+use crate::weapon::Weapon;
+use crate::{log, Result};
+use std::error::Error as StdError;
+use std::fmt;
+use std::mem;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// ID of the next match.
+static NEXT_MATCH_ID: AtomicU64 = AtomicU64::new(1);
 
 /// Maximum number of players.
 pub const MAX_PLAYERS: usize = 2;
+/// Usage of the special weapons.
+const WEAPONS_HELP: &str = "Special weapons: sonar <coordinate>, airstrike <coordinate>, \
+torpedo <coordinate> <direction (n/e/s/w)>\n";
+/// Usage of the move command.
+const MOVE_HELP: &str =
+    "Move an undamaged ship instead of firing: move <coordinate> <direction (n/e/s/w)>\n";
+/// Name of the command for moving ships.
+const MOVE_COMMAND: &str = "move";
+/// Name of the command for firing a single shot.
+const FIRE_COMMAND: &str = "fire";
+
+/// Available commands during a turn.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Fire a single shot (e.g. "c4").
+    Fire(Coordinate),
+    /// Use sonar around the coordinate (e.g. "sonar c4").
+    Sonar(Coordinate),
+    /// Launch an airstrike centered on the coordinate (e.g. "airstrike c4").
+    Airstrike(Coordinate),
+    /// Launch a torpedo from the coordinate towards the direction (e.g. "torpedo c4 e").
+    Torpedo(Coordinate, Orientation),
+    /// Move the own ship at the coordinate one cell towards the direction (e.g. "move c4 e").
+    Move(Coordinate, Orientation),
+}
+
+impl FromStr for Command {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let value = value.trim();
+        let mut parts = value.splitn(2, char::is_whitespace);
+        let (name, args) = (
+            parts.next().unwrap_or_default(),
+            parts.next().unwrap_or_default().trim(),
+        );
+        let directed = || -> StdResult<(Coordinate, Orientation), Self::Err> {
+            let mut parts = args.rsplitn(2, char::is_whitespace);
+            match (parts.next(), parts.next()) {
+                (Some(direction), Some(coordinate)) => {
+                    Ok((coordinate.parse()?, direction.parse()?))
+                }
+                _ => Err(format!(
+                    "{} needs a coordinate and a direction (e.g. {} c4 e)",
+                    name, name
+                )
+                .into()),
+            }
+        };
+        if name.eq_ignore_ascii_case(FIRE_COMMAND) {
+            return Ok(Self::Fire(args.parse()?));
+        }
+        if name.eq_ignore_ascii_case(MOVE_COMMAND) {
+            let (coordinate, direction) = directed()?;
+            return Ok(Self::Move(coordinate, direction));
+        }
+        match Weapon::from_name(name) {
+            None => Ok(Self::Fire(value.parse()?)),
+            Some(Weapon::Sonar) => Ok(Self::Sonar(args.parse()?)),
+            Some(Weapon::Airstrike) => Ok(Self::Airstrike(args.parse()?)),
+            Some(Weapon::Torpedo) => {
+                let (coordinate, direction) = directed()?;
+                Ok(Self::Torpedo(coordinate, direction))
+            }
+        }
+    }
+}
+
+/// Display the command in the form that it can be parsed back, e.g. "torpedo C4 east".
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fire(coordinate) => write!(f, "{} {}", FIRE_COMMAND, coordinate),
+            Self::Move(coordinate, direction) => {
+                write!(f, "{} {} {}", MOVE_COMMAND, coordinate, direction)
+            }
+            Self::Torpedo(coordinate, direction) => {
+                write!(f, "{} {} {}", Weapon::Torpedo, coordinate, direction)
+            }
+            Self::Sonar(coordinate) => write!(f, "{} {}", Weapon::Sonar, coordinate),
+            Self::Airstrike(coordinate) => write!(f, "{} {}", Weapon::Airstrike, coordinate),
+        }
+    }
+}
+
+impl Command {
+    /// Returns the target coordinate of the command.
+    pub fn target(&self) -> Coordinate {
+        match self {
+            Self::Fire(coordinate)
+            | Self::Sonar(coordinate)
+            | Self::Airstrike(coordinate)
+            | Self::Torpedo(coordinate, _)
+            | Self::Move(coordinate, _) => *coordinate,
+        }
+    }
+
+    /// Returns the special weapon that is used by the command.
+    pub fn weapon(&self) -> Option<Weapon> {
+        match self {
+            Self::Fire(_) | Self::Move(_, _) => None,
+            Self::Sonar(_) => Some(Weapon::Sonar),
+            Self::Airstrike(_) => Some(Weapon::Airstrike),
+            Self::Torpedo(_, _) => Some(Weapon::Torpedo),
+        }
+    }
+
+    /// Returns whether if the command fires at the opponent's ships.
+    pub fn is_shot(&self) -> bool {
+        !matches!(self, Self::Sonar(_) | Self::Move(_, _))
+    }
+}
+
+/// Read-only observer of a game.
+#[derive(Debug)]
+pub struct Spectator {
+    /// Connection of the spectator.
+    pub player: Player,
+    /// Whether if the ships of both players are shown.
+    pub omniscient: bool,
+}
+
+impl Spectator {
+    /// Constructs a new instance of [`Spectator`].
+    pub fn new(player: Player, omniscient: bool) -> Self {
+        Self { player, omniscient }
+    }
+}
 
 /// Representation of the Battleship game.
 ///
 /// Handles the turns and game logic.
 #[derive(Default, Debug)]
 pub struct Game {
+    /// Spectators of the game.
+    ///
+    /// Spectators are shared since they can join while the game is running.
+    pub spectators: Arc<Mutex<Vec<Spectator>>>,
     /// Players of the game.
     pub players: Vec<Player>,
+    /// Rules of the game.
+    pub rules: Rules,
+    /// Record of the current match.
+    ///
+    /// It is kept after the match ends, even if the match is abandoned.
+    pub record: Record,
+    /// Length of the countdown before the game in seconds.
+    pub countdown: u64,
+    /// Time limit of a turn.
+    pub turn_timeout: Option<Duration>,
+    /// ID of the current match.
+    pub match_id: u64,
+    /// Registry of the connections and matches.
+    pub registry: Arc<Registry>,
 }
 
 impl Game {
@@ -31,7 +193,8 @@ impl Game {
     ///
     /// Also see [`Game::is_ready`]
     pub fn add_player(&mut self, player: Player) -> Result<()> {
-        if self.players.first().is_none() {
+        self.registry.set_status(player.id, Status::Waiting);
+        if self.players.is_empty() {
             self.players.push(player);
             self.players[0].send("Waiting for opponent...\n")?;
         } else {
@@ -44,49 +207,135 @@ impl Game {
                 self.players[i].send(&message)?;
             }
         }
+        METRICS.set_players_waiting(if self.is_ready() {
+            0
+        } else {
+            self.players.len()
+        });
         Ok(())
     }
 
+    /// Returns the log context for the given player in the current match.
+    fn context(&self, player: usize) -> Context {
+        Context::game(self.match_id).with_player(self.players[player].id)
+    }
+
     /// Shows countdown to players for starting the game.
+    ///
+    /// Also shows the usage of the special weapons and moves if they are available.
     fn show_countdown(&mut self) -> Result<()> {
-        println!("[#] Game is starting.");
-        for i in 1..4 {
-            let message = format!("Game starts in {}...\n", 4 - i);
+        log!(Info, Context::game(self.match_id), "Game is starting.");
+        if self.rules.mode == Mode::Mobile {
+            self.players
+                .iter_mut()
+                .try_for_each(|p| p.send(MOVE_HELP))?;
+        }
+        self.players
+            .iter_mut()
+            .filter(|p| !p.ammo.is_empty())
+            .try_for_each(|p| p.send(WEAPONS_HELP))?;
+        for i in (1..=self.countdown).rev() {
+            let message = format!("Game starts in {}...\n", i);
             self.players.iter_mut().try_for_each(|p| p.send(&message))?;
             thread::sleep(Duration::from_secs(1));
         }
         Ok(())
     }
 
+    /// Returns the message for the result of a shot.
+    fn describe_shot(state: CellState) -> &'static str {
+        match state {
+            CellState::Hit => "Hit!",
+            CellState::Sunk => "Hit and sunk!",
+            _ => "Missed.",
+        }
+    }
+
+    /// Returns the message for the results of multiple shots.
+    ///
+    /// e.g. "C4: Missed. D4: Hit!"
+    fn describe_shots(results: &[(Coordinate, CellState)]) -> String {
+        let mut message = results
+            .iter()
+            .map(|(coordinate, state)| format!("{}: {}", coordinate, Self::describe_shot(*state)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        message.push('\n');
+        message
+    }
+
+    /// Fires the given command at the grid.
+    ///
+    /// Returns the resulting states of the affected cells.
+    /// Sonar results are reported as [`CellState::Detected`] or [`CellState::Clear`]
+    /// even if the scanned cell is already shot at.
+    fn fire(grid: &mut Grid, command: Command) -> Vec<(Coordinate, CellState)> {
+        match command {
+            Command::Fire(coordinate) => vec![(coordinate, grid.shoot(coordinate))],
+            Command::Sonar(coordinate) => {
+                let state = if grid.sonar(coordinate) {
+                    CellState::Detected
+                } else {
+                    CellState::Clear
+                };
+                vec![(coordinate, state)]
+            }
+            Command::Airstrike(coordinate) => grid.airstrike(coordinate),
+            Command::Torpedo(coordinate, direction) => grid.torpedo(coordinate, direction),
+            Command::Move(_, _) => Vec::new(),
+        }
+    }
+
+    /// Shows the grids of the players to the spectators along with the given message.
+    ///
+    /// Tracking grids are shown unless the spectator is omniscient.
+    /// Spectators are not waited for: the ones that are disconnected or
+    /// cannot keep up with the game are removed.
+    fn show_spectators(&mut self, message: &str) -> Result<()> {
+        let mut spectators = mem::take(
+            &mut *self
+                .spectators
+                .lock()
+                .expect("failed to retrieve spectators"),
+        );
+        if spectators.is_empty() {
+            return Ok(());
+        }
+        let mut views = Vec::new();
+        for omniscient in &[false, true] {
+            let mut view = String::new();
+            for player in &self.players {
+                view += &format!(
+                    "{}'s grid:{}",
+                    player.name,
+                    player.grid.as_string(*omniscient)?
+                );
+            }
+            view += message;
+            views.push(view);
+        }
+        spectators.retain_mut(|spectator| {
+            let view = &views[usize::from(spectator.omniscient)];
+            spectator.player.send(view).is_ok()
+        });
+        // Keep the spectators that joined in the meantime.
+        let mut joined = self
+            .spectators
+            .lock()
+            .expect("failed to retrieve spectators");
+        spectators.append(&mut joined);
+        *joined = spectators;
+        Ok(())
+    }
+
     /// Shows the grid of the players.
     ///
     /// Hits/misses are shown on the upper grid.
     /// Lower grid is used for showing the player ships.
-    fn show_grid(&mut self, width: u8, height: u8) -> Result<()> {
+    fn show_grid(&mut self) -> Result<()> {
         for i in 0..MAX_PLAYERS {
             // Show upper grid (hits/misses).
-            let ships = self.players[i]
-                .hits
-                .iter()
-                .map(|coord| Ship {
-                    coords: vec![Coordinate {
-                        x: coord.x,
-                        y: coord.y,
-                        is_hit: self.players[MAX_PLAYERS - (i + 1)]
-                            .grid
-                            .ships
-                            .iter()
-                            .any(|ship| ship.coords.contains(coord)),
-                    }],
-                    ..Default::default()
-                })
-                .collect();
-            let grid_str = Grid {
-                width,
-                height,
-                ships,
-            }
-            .as_string(false)?;
+            let grid_str = self.players[MAX_PLAYERS - (i + 1)].grid.as_string(false)?;
             self.players[i].send(&grid_str)?;
 
             // Show lower grid (ships).
@@ -101,57 +350,189 @@ impl Game {
     /// Number of players is determined by [`MAX_PLAYERS`] constant.
     /// Game loop continues until one of the players hits all of the ships of the opponent.
     /// Lower and upper grids are shown along with extra messages during the gameplay.
-    pub fn start(&mut self, grid_width: u8, grid_height: u8) -> Result<()> {
+    /// The match is recorded to [`Game::record`], which has no winner if the match is abandoned.
+    /// Match stays in the registry until it is ended with [`Registry::end_match`] after the
+    /// record is handled, so that the server does not stop in the meantime.
+    pub fn start(&mut self) -> Result<()> {
+        self.match_id = NEXT_MATCH_ID.fetch_add(1, Ordering::Relaxed);
+        METRICS.match_started();
+        let players = self.players.iter().map(|p| p.id).collect::<Vec<u64>>();
+        self.registry.start_match(self.match_id, &players);
+        self.record = Record::new(self.rules.clone(), self.players[0].grid.map().clone());
+        self.record.players = self
+            .players
+            .iter()
+            .map(|player| PlayerRecord {
+                name: player.name.to_string(),
+                ships: player.grid.ships().to_vec(),
+            })
+            .collect();
+        let started = Instant::now();
+        let result = self.play();
+        self.record.finished = record::now();
+        match result {
+            Ok(_) => METRICS.match_completed(started.elapsed()),
+            Err(_) => METRICS.match_aborted(),
+        }
+        result
+    }
+
+    /// Runs the game loop.
+    ///
+    /// See [`Game::start`].
+    fn play(&mut self) -> Result<()> {
         self.show_countdown()?;
+        let message = format!(
+            "Game started: {} vs {}\n",
+            self.players[0].name, self.players[1].name
+        );
+        self.show_spectators(&message)?;
         'game: loop {
             for i in 0..MAX_PLAYERS {
                 // Check if the player has won.
-                if self.players[i].grid.ships.iter().all(|ship| ship.is_sunk()) {
+                if self.players[i].grid.is_cleared() {
                     let message = format!("{} won.\n", self.players[MAX_PLAYERS - (i + 1)].name);
                     self.players[i].send(&message)?;
                     self.players[MAX_PLAYERS - (i + 1)].send("You won!\n")?;
+                    self.show_spectators(&message)?;
+                    log!(
+                        Info,
+                        self.context(MAX_PLAYERS - (i + 1)),
+                        "{}",
+                        message.trim_end()
+                    );
                     self.players.clear();
-                    print!("[#] {}", message);
+                    self.record.winner = Some(MAX_PLAYERS - (i + 1));
                     break 'game;
                 }
 
                 // Show the grid.
-                self.show_grid(grid_width, grid_height)?;
+                self.show_grid()?;
 
                 // Handle the player turn.
-                self.players[i].send("Your turn: ")?;
+                let prompt = if self.players[i].ammo.is_empty() {
+                    String::from("Your turn: ")
+                } else {
+                    format!("Your turn ({}): ", self.players[i].ammo)
+                };
+                self.players[i].send(&prompt)?;
                 let message = format!("{}'s turn.\n", self.players[i].name);
-                print!("[#] {}", message);
+                log!(Info, self.context(i), "{}", message.trim_end());
                 self.players[MAX_PLAYERS - (i + 1)].send(&message)?;
 
-                // Parse the grid coordinate.
-                let coordinate_str = self.players[i].read()?;
-                let coordinate =
-                    if let Ok(coordinate) = Coordinate::try_from(coordinate_str.to_string()) {
-                        println!(
-                            "[#] {} is firing a shot: {} ({:?})",
-                            self.players[i].name, coordinate_str, coordinate
+                // Parse the command.
+                let command_str = match self.players[i].read_timeout(self.turn_timeout)? {
+                    Some(command_str) => command_str,
+                    None => {
+                        self.players[i].send("\nTime is up! Your turn is skipped.\n")?;
+                        let message = format!("{} ran out of time.\n", self.players[i].name);
+                        log!(Info, self.context(i), "{}", message.trim_end());
+                        self.players[MAX_PLAYERS - (i + 1)].send(&message)?;
+                        continue;
+                    }
+                };
+                let command = match Command::from_str(&command_str) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        let message = format!("Your missile went to space! ({})\n", e);
+                        self.players[i].send(&message)?;
+                        continue;
+                    }
+                };
+                let coordinate = command.target();
+                if let Command::Move(coordinate, direction) = command {
+                    if self.rules.mode != Mode::Mobile {
+                        self.players[i].send("Ships cannot be moved in this mode!\n")?;
+                    } else if self.players[i].grid.move_ship(coordinate, direction) {
+                        log!(
+                            Info,
+                            self.context(i),
+                            "{} moved the ship at {} ({:?})",
+                            self.players[i].name,
+                            coordinate,
+                            direction
                         );
-                        coordinate
+                        self.players[i].send("Your ship moved.\n")?;
+                        self.record.turns.push(Turn {
+                            time: record::now(),
+                            player: i,
+                            command,
+                            results: Vec::new(),
+                        });
+                        let message = format!("{} moved a ship.\n", self.players[i].name);
+                        self.players[MAX_PLAYERS - (i + 1)].send(&message)?;
+                        self.show_spectators(&message)?;
                     } else {
-                        self.players[i].send("Your missile went to space!\n")?;
+                        let message = format!("You cannot move the ship at {}!\n", coordinate);
+                        self.players[i].send(&message)?;
+                    }
+                    continue;
+                }
+                match self.players[MAX_PLAYERS - (i + 1)].grid.tile(coordinate) {
+                    Tile::Water => {}
+                    Tile::Land => {
+                        let message =
+                            format!("Your missile crashed into an island at {}!\n", coordinate);
+                        self.players[i].send(&message)?;
+                        continue;
+                    }
+                    Tile::Void => {
+                        let message = format!(
+                            "Your missile went to space! ({} is outside the grid)\n",
+                            coordinate
+                        );
+                        self.players[i].send(&message)?;
                         continue;
-                    };
+                    }
+                }
+                if let Some(weapon) = command.weapon() {
+                    if !self.players[i].ammo.take(weapon) {
+                        let message = format!("You have no {} left!\n", weapon);
+                        self.players[i].send(&message)?;
+                        continue;
+                    }
+                }
+                log!(
+                    Info,
+                    self.context(i),
+                    "{} is firing a shot: {}",
+                    self.players[i].name,
+                    command
+                );
 
                 // Handle hit/miss.
-                self.players[i].hits.push(coordinate);
-                if let Some(coordinate) = self.players[MAX_PLAYERS - (i + 1)]
-                    .grid
-                    .ships
-                    .iter_mut()
-                    .find(|ship| ship.coords.contains(&coordinate))
-                    .and_then(|ship| ship.coords.iter_mut().find(|c| *c == &coordinate))
-                {
-                    coordinate.is_hit = true;
-                    self.players[i].send("Hit!\n")?;
-                } else {
-                    self.players[i].send("Missed.\n")?;
+                let results = Self::fire(&mut self.players[MAX_PLAYERS - (i + 1)].grid, command);
+                let message = match command {
+                    Command::Fire(_) => format!("{}\n", Self::describe_shot(results[0].1)),
+                    Command::Sonar(coordinate) if results[0].1 == CellState::Detected => {
+                        format!("Sonar detected a ship around {}!\n", coordinate)
+                    }
+                    Command::Sonar(coordinate) => {
+                        format!("Sonar detected no ships around {}.\n", coordinate)
+                    }
+                    _ => Self::describe_shots(&results),
+                };
+                self.record.turns.push(Turn {
+                    time: record::now(),
+                    player: i,
+                    command,
+                    results,
+                });
+                if command.is_shot() {
+                    self.players[i].hits.push(coordinate);
+                    METRICS.shot_fired();
                 }
+                self.players[i].send(&message)?;
+                log!(
+                    Debug,
+                    self.context(i),
+                    "{} -> {}",
+                    command,
+                    message.trim_end()
+                );
+                let spectator_message =
+                    format!("{}: {} -> {}", self.players[i].name, command, message);
+                self.show_spectators(&spectator_message)?;
 
                 // Inform about the game stats.
                 let message = format!(
@@ -159,16 +540,136 @@ impl Game {
                     self.players[MAX_PLAYERS - (i + 1)].name,
                     self.players[MAX_PLAYERS - (i + 1)]
                         .grid
-                        .ships
+                        .ships()
                         .iter()
                         .filter(|ship| !ship.is_sunk())
                         .count()
                 );
                 self.players[i].send(&message)?;
-                let message = format!("{} is firing at {}\n", self.players[i].name, coordinate);
+                let message = match command.weapon() {
+                    Some(weapon) => format!(
+                        "{} used {} at {}\n",
+                        self.players[i].name, weapon, coordinate
+                    ),
+                    None => format!("{} is firing at {}\n", self.players[i].name, coordinate),
+                };
                 self.players[MAX_PLAYERS - (i + 1)].send(&message)?;
             }
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::{Ship, ShipType};
+    use std::net::{TcpListener, TcpStream};
+    use std::time::Instant;
+
+    #[test]
+    fn test_command() -> Result<()> {
+        assert_eq!(
+            Command::Fire(Coordinate::from((3, 4))),
+            Command::from_str("c4")?
+        );
+        assert_eq!(
+            Command::Sonar(Coordinate::from((3, 4))),
+            Command::from_str(" sonar C 4\n")?
+        );
+        assert_eq!(
+            Command::Airstrike(Coordinate::from((1, 10))),
+            Command::from_str("airstrike a10")?
+        );
+        assert_eq!(
+            Command::Torpedo(Coordinate::from((3, 4)), Orientation::East),
+            Command::from_str("torpedo c-4 e")?
+        );
+        assert_eq!(
+            Some(Weapon::Torpedo),
+            Command::from_str("torpedo c4 n")?.weapon()
+        );
+        assert!(!Command::from_str("sonar c4")?.is_shot());
+        assert!(Command::from_str("torpedo c4").is_err());
+        assert!(Command::from_str("sonar").is_err());
+        assert!(Command::from_str("nuke c4").is_err());
+        let command = Command::from_str("MOVE b2 w")?;
+        assert_eq!(
+            Command::Move(Coordinate::from((2, 2)), Orientation::West),
+            command
+        );
+        assert!(!command.is_shot());
+        assert_eq!(None, command.weapon());
+        assert!(Command::from_str("move b2").is_err());
+        assert_eq!(
+            Command::Fire(Coordinate::from((3, 4))),
+            Command::from_str("fire c4")?
+        );
+        for command in &[
+            "fire C4",
+            "sonar C4",
+            "airstrike C4",
+            "torpedo C4 south",
+            "move C4 west",
+        ] {
+            assert_eq!(*command, Command::from_str(command)?.to_string());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fire() {
+        let mut grid = Grid::new(5, 5);
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::East),
+            ShipType::Destroyer(Orientation::East).get_hitbox(Coordinate::from((2, 2)))
+        )));
+        assert!(grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((5, 5))])));
+        let coordinate = Coordinate::from((2, 2));
+        assert_eq!(
+            vec![(coordinate, CellState::Hit)],
+            Game::fire(&mut grid, Command::Fire(coordinate))
+        );
+        assert_eq!(
+            vec![(coordinate, CellState::Detected)],
+            Game::fire(&mut grid, Command::Sonar(coordinate))
+        );
+        let coordinate = Coordinate::from((5, 5));
+        assert_eq!(
+            vec![(coordinate, CellState::Sunk)],
+            Game::fire(&mut grid, Command::Fire(coordinate))
+        );
+        assert_eq!(
+            vec![(coordinate, CellState::Clear)],
+            Game::fire(&mut grid, Command::Sonar(coordinate))
+        );
+        assert!(Game::fire(&mut grid, Command::Move(coordinate, Orientation::North)).is_empty());
+    }
+
+    #[test]
+    fn test_slow_spectator() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let client = TcpStream::connect(listener.local_addr()?)?;
+        let player = Player::new(listener.accept()?.0);
+        player.set_nonblocking(true)?;
+        let mut game = Game::default();
+        game.spectators
+            .lock()
+            .expect("failed to retrieve spectators")
+            .push(Spectator::new(player, false));
+        // The client never reads, so the spectator falls behind and gets removed.
+        let message = "~".repeat(64 * 1024);
+        let started = Instant::now();
+        for _ in 0..1000 {
+            game.show_spectators(&message)?;
+        }
+        assert!(game
+            .spectators
+            .lock()
+            .expect("failed to retrieve spectators")
+            .is_empty());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        drop(client);
+        Ok(())
+    }
+}
@@ -1,21 +1,90 @@
 //! Game board.
 
-use crate::ship::{Ship, ShipType};
+use crate::map::{Map, Tile};
+use crate::rules::Fleet;
+use crate::ship::{Orientation, Ship, ShipType};
 use crate::Result;
 use std::convert::TryFrom;
 use std::fmt;
 use std::io::{Result as IoResult, Write};
 use std::result::Result as StdResult;
-use std::str;
+use std::str::{self, FromStr};
 
 /// Available alphabet characters for column names.
 pub const ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz";
+/// Maximum number of columns/rows on the grid.
+pub const MAX_GRID_SIZE: u8 = 255;
+/// Maximum number of attempts for placing random ships.
+pub const MAX_PLACEMENT_ATTEMPTS: usize = 1000;
 /// The character that represents a hit.
 const HIT_POINT: &str = "☒";
 /// The character that represents a miss.
 const MISSED_POINT: &str = "✕";
+/// The character that represents a part of a sunk ship.
+const SUNK_POINT: &str = "■";
 /// The character to display a default coordinate.
 const DEFAULT_POINT: &str = "•";
+/// The character that represents a cell where sonar detected a ship.
+const DETECTED_POINT: &str = "?";
+/// The character that represents a cell where sonar detected no ships.
+const CLEAR_POINT: &str = "○";
+/// Size of the square area that is scanned by sonar.
+pub const SONAR_SIZE: u8 = 3;
+/// Length of the row segment that is hit by an airstrike.
+pub const AIRSTRIKE_LENGTH: u8 = 5;
+
+/// Possible states of a cell from the shooter's point of view.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CellState {
+    /// Cell is not shot at yet.
+    Unknown,
+    /// Shot landed on the water.
+    Miss,
+    /// Shot hit a part of a ship.
+    Hit,
+    /// Shot hit a part of a ship that is sunk.
+    Sunk,
+    /// Cell is not shot at yet but sonar detected a ship around it.
+    Detected,
+    /// Cell is not shot at yet but sonar detected no ships around it.
+    Clear,
+}
+
+impl CellState {
+    /// Returns the cell state that is represented by the given point on the grid.
+    ///
+    /// This is the reverse of [`CellState`]'s `Display` implementation.
+    pub fn from_point(point: &str) -> Option<Self> {
+        vec![
+            Self::Unknown,
+            Self::Miss,
+            Self::Hit,
+            Self::Sunk,
+            Self::Detected,
+            Self::Clear,
+        ]
+        .into_iter()
+        .find(|state| state.to_string() == point)
+    }
+}
+
+/// Display the cell state as a string.
+impl fmt::Display for CellState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CellState::Unknown => DEFAULT_POINT,
+                CellState::Miss => MISSED_POINT,
+                CellState::Hit => HIT_POINT,
+                CellState::Sunk => SUNK_POINT,
+                CellState::Detected => DETECTED_POINT,
+                CellState::Clear => CLEAR_POINT,
+            }
+        )
+    }
+}
 
 /// Representation of coordinates on a 2-dimensional plane.
 #[derive(Clone, Copy, Default)]
@@ -45,35 +114,145 @@ impl fmt::Debug for Coordinate {
     }
 }
 
+/// Returns the spreadsheet-style name of the column at the given index.
+///
+/// e.g. 1 to "A", 26 to "Z", 27 to "AA", 28 to "AB" and so on.
+pub fn column_name(index: u8) -> String {
+    let alphabet_chars = ALPHABET.chars().collect::<Vec<char>>();
+    let mut name = Vec::new();
+    let mut index = usize::from(index);
+    while index > 0 {
+        index -= 1;
+        name.push(alphabet_chars[index % ALPHABET.len()].to_ascii_uppercase());
+        index /= ALPHABET.len();
+    }
+    name.iter().rev().collect()
+}
+
+/// Returns the index of the column with the given spreadsheet-style name.
+///
+/// Returns `None` if the name is invalid or exceeds [`MAX_GRID_SIZE`].
+pub fn column_index(name: &str) -> Option<u8> {
+    if name.is_empty() {
+        return None;
+    }
+    name.to_lowercase().chars().try_fold(0u8, |index, c| {
+        let value = ALPHABET.find(c)? as u8 + 1;
+        index.checked_mul(ALPHABET.len() as u8)?.checked_add(value)
+    })
+}
+
 /// For converting between e.g. (10, 10) to "J10"
 impl fmt::Display for Coordinate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let alphabet_chars = ALPHABET.chars().collect::<Vec<char>>();
-        let index = self.x.checked_sub(1).unwrap_or_default() as usize;
-        write!(f, "{}{}", alphabet_chars[index].to_uppercase(), self.y)
+        write!(f, "{}{}", column_name(self.x), self.y)
     }
 }
 
-/// For converting between e.g. "J10" to (10, 10)
-impl TryFrom<String> for Coordinate {
-    type Error = ();
-    fn try_from(mut value: String) -> StdResult<Self, Self::Error> {
-        value = value.to_lowercase();
-        let mut coordinate = Coordinate::default();
-        for (i, c) in ALPHABET.chars().enumerate() {
-            if value.starts_with(&c.to_string()) {
-                value = value.trim_start_matches(c).to_string();
-                coordinate.x = i as u8 + 1;
+/// Possible errors while parsing a [`Coordinate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoordinateParseError {
+    /// Input is empty.
+    Empty,
+    /// Column letters are missing.
+    MissingColumn,
+    /// Row number is missing.
+    MissingRow,
+    /// Input contains an unexpected character.
+    InvalidCharacter(char),
+    /// Column is not in the supported range.
+    ColumnOutOfRange(String),
+    /// Row is not in the supported range.
+    RowOutOfRange(String),
+}
+
+impl fmt::Display for CoordinateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no coordinate is given"),
+            Self::MissingColumn => write!(f, "column letter is missing"),
+            Self::MissingRow => write!(f, "row number is missing"),
+            Self::InvalidCharacter(c) => write!(f, "unexpected character '{}'", c),
+            Self::ColumnOutOfRange(column) => {
+                write!(f, "column {} is out of range", column.to_uppercase())
             }
+            Self::RowOutOfRange(row) => write!(f, "row {} is out of range", row),
         }
-        if coordinate.x == 0 {
-            Err(())
-        } else if let Ok(y) = value.parse() {
-            coordinate.y = y;
-            Ok(coordinate)
-        } else {
-            Err(())
+    }
+}
+
+impl std::error::Error for CoordinateParseError {}
+
+/// Splits the string at the first character that does not match the predicate.
+fn split_while<P: Fn(char) -> bool>(value: &str, predicate: P) -> (&str, &str) {
+    match value.find(|c: char| !predicate(c)) {
+        Some(i) => value.split_at(i),
+        None => (value, ""),
+    }
+}
+
+/// Skips the separator between the column and the row (e.g. "A-1" or "A 1").
+fn skip_separator(value: &str) -> &str {
+    let value = value.trim_start();
+    match value.strip_prefix('-') {
+        Some(value) => value.trim_start(),
+        None => value,
+    }
+}
+
+/// For converting between e.g. "J10" to (10, 10)
+///
+/// The column and the row can be given in any order and they might be
+/// separated by whitespace or a dash. (e.g. "J10", "j 10", "10J", "J-10")
+impl FromStr for Coordinate {
+    type Err = CoordinateParseError;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let value = value.trim();
+        let (column, row, rest) = match value.chars().next() {
+            None => return Err(CoordinateParseError::Empty),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let (column, rest) = split_while(value, |c| c.is_ascii_alphabetic());
+                let (row, rest) = split_while(skip_separator(rest), |c| c.is_ascii_digit());
+                (column, row, rest)
+            }
+            Some(c) if c.is_ascii_digit() => {
+                let (row, rest) = split_while(value, |c| c.is_ascii_digit());
+                let (column, rest) = split_while(skip_separator(rest), |c| c.is_ascii_alphabetic());
+                (column, row, rest)
+            }
+            Some(c) => return Err(CoordinateParseError::InvalidCharacter(c)),
+        };
+        if let Some(c) = rest.trim_start().chars().next() {
+            return Err(CoordinateParseError::InvalidCharacter(c));
+        } else if column.is_empty() {
+            return Err(CoordinateParseError::MissingColumn);
+        } else if row.is_empty() {
+            return Err(CoordinateParseError::MissingRow);
         }
+        let x = column_index(column)
+            .ok_or_else(|| CoordinateParseError::ColumnOutOfRange(column.to_string()))?;
+        let y = row
+            .parse::<u8>()
+            .ok()
+            .filter(|y| *y != 0)
+            .ok_or_else(|| CoordinateParseError::RowOutOfRange(row.to_string()))?;
+        Ok(Coordinate::from((x, y)))
+    }
+}
+
+/// See [`Coordinate::from_str`].
+impl TryFrom<&str> for Coordinate {
+    type Error = CoordinateParseError;
+    fn try_from(value: &str) -> StdResult<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+/// See [`Coordinate::from_str`].
+impl TryFrom<String> for Coordinate {
+    type Error = CoordinateParseError;
+    fn try_from(value: String) -> StdResult<Self, Self::Error> {
+        value.parse()
     }
 }
 
@@ -89,75 +268,388 @@ impl From<(u8, u8)> for Coordinate {
 }
 
 /// Representation of the game grid.
-#[derive(Default, Debug)]
+///
+/// Cells are indexed in a flat, row-major array that is kept in sync
+/// with the placed ships for constant time lookups.
+#[derive(Clone, Default, Debug)]
 pub struct Grid {
-    /// Width.
-    pub width: u8,
-    /// Height.
-    pub height: u8,
+    /// Map that the grid is built on.
+    map: Map,
     /// Ships on the grid.
-    pub ships: Vec<Ship>,
+    ships: Vec<Ship>,
+    /// Index of the ship that occupies the cell.
+    cells: Vec<Option<usize>>,
+    /// Whether if the cell is shot at.
+    shots: Vec<bool>,
+    /// Sonar results of the cell.
+    scans: Vec<Option<bool>>,
 }
 
 impl Grid {
     /// Constructs a new instance of [`Grid`].
     pub fn new(width: u8, height: u8) -> Self {
+        Self::from_map(Map::new(width, height))
+    }
+
+    /// Constructs a new instance of [`Grid`] on the given map.
+    pub fn from_map(map: Map) -> Self {
+        let size = map.tiles().len();
         Self {
-            width,
-            height,
+            map,
             ships: Vec::new(),
+            cells: vec![None; size],
+            shots: vec![false; size],
+            scans: vec![None; size],
         }
     }
 
     /// Constructs a new instance of [`Grid`] with random ships and placements.
     ///
-    /// Allows only one [`Battleship`] on the grid.
+    /// Also see [`Grid::place_random_ships`].
+    pub fn new_random(width: u8, height: u8) -> Self {
+        let mut grid = Grid::new(width, height);
+        grid.place_random_ships(&Fleet::default());
+        grid
+    }
+
+    /// Places random ships of the given fleet on the grid.
+    ///
+    /// Allows only one [`Battleship`] on the grid for the [`Orhun`] fleet.
+    /// Gives up after [`MAX_PLACEMENT_ATTEMPTS`] if the map does not have enough water.
     /// Also see [`Ship::new_random`].
     ///
     /// [`Battleship`]: ShipType::Battleship
-    pub fn new_random(width: u8, height: u8) -> Self {
-        let mut grid = Grid::new(width, height);
-        let ship_count = fastrand::usize(4..=7);
-        let mut battleship = None;
-        while grid.ships.len() != ship_count {
-            let ship = Ship::new_random(grid.width, grid.height);
-            if let ShipType::Battleship(_) = ship.type_ {
-                battleship = Some(ship);
-            } else {
-                grid.place_ship(ship);
+    /// [`Orhun`]: Fleet::Orhun
+    pub fn place_random_ships(&mut self, fleet: &Fleet) {
+        match fleet {
+            Fleet::Classic => {
+                for ship_type in Fleet::classic_ship_types() {
+                    for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+                        let ship =
+                            Ship::new_random_with_type(ship_type, self.width(), self.height());
+                        if self.place_ship(ship) {
+                            break;
+                        }
+                    }
+                }
+            }
+            Fleet::Orhun => {
+                let ship_count = self.ships.len() + fastrand::usize(4..=7);
+                let mut battleship = None;
+                for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+                    if self.ships.len() == ship_count {
+                        break;
+                    }
+                    let ship = Ship::new_random(self.width(), self.height());
+                    if let ShipType::Battleship(_) = ship.type_ {
+                        battleship = Some(ship);
+                    } else {
+                        self.place_ship(ship);
+                    }
+                }
+                if let Some(battleship) = battleship {
+                    self.place_ship(battleship);
+                }
+            }
+            Fleet::Custom(definitions) => {
+                for definition in definitions {
+                    for _ in 0..definition.count {
+                        for _ in 0..MAX_PLACEMENT_ATTEMPTS {
+                            let ship = Ship::new_random_from_definition(
+                                definition,
+                                self.width(),
+                                self.height(),
+                            );
+                            if self.place_ship(ship) {
+                                break;
+                            }
+                        }
+                    }
+                }
             }
         }
-        if let Some(battleship) = battleship {
-            grid.place_ship(battleship);
+    }
+
+    /// Returns the width of the grid.
+    pub fn width(&self) -> u8 {
+        self.map.width()
+    }
+
+    /// Returns the height of the grid.
+    pub fn height(&self) -> u8 {
+        self.map.height()
+    }
+
+    /// Returns the map that the grid is built on.
+    pub fn map(&self) -> &Map {
+        &self.map
+    }
+
+    /// Returns the tile at the given coordinate.
+    pub fn tile(&self, coordinate: Coordinate) -> Tile {
+        self.map.tile(coordinate)
+    }
+
+    /// Returns the ships on the grid.
+    pub fn ships(&self) -> &[Ship] {
+        &self.ships
+    }
+
+    /// Returns the coordinates of the shots that missed the ships.
+    pub fn misses(&self) -> Vec<Coordinate> {
+        (1..=self.height())
+            .flat_map(|y| (1..=self.width()).map(move |x| Coordinate::from((x, y))))
+            .filter(|coord| self.cell_state(*coord) == CellState::Miss)
+            .collect()
+    }
+
+    /// Returns whether if the given coordinate is inside the map outline.
+    pub fn contains(&self, coordinate: Coordinate) -> bool {
+        self.tile(coordinate) != Tile::Void
+    }
+
+    /// Returns the index of the cell at the given coordinate.
+    ///
+    /// Returns `None` if the coordinate is outside the grid.
+    fn index(&self, coordinate: Coordinate) -> Option<usize> {
+        if (1..=self.width()).contains(&coordinate.x) && (1..=self.height()).contains(&coordinate.y)
+        {
+            Some(
+                usize::from(coordinate.y - 1) * usize::from(self.width())
+                    + usize::from(coordinate.x - 1),
+            )
+        } else {
+            None
         }
-        grid
+    }
+
+    /// Returns the ship at the given coordinate.
+    pub fn ship_at(&self, coordinate: Coordinate) -> Option<&Ship> {
+        self.index(coordinate)
+            .and_then(|i| self.cells[i])
+            .map(|ship| &self.ships[ship])
     }
 
     /// Places a ship on the grid.
     ///
-    /// Returns `false` if the ship is overlapping with other ships
-    /// or placed outside the grid.
+    /// Returns `false` if the ship is overlapping with other ships,
+    /// placed on land or outside the grid.
     /// Returns `true` if the placement is successful.
     pub fn place_ship(&mut self, ship: Ship) -> bool {
-        let overlaps = self
-            .ships
+        match self.free_indexes(&ship.coords, None) {
+            Some(indexes) => {
+                indexes
+                    .into_iter()
+                    .for_each(|i| self.cells[i] = Some(self.ships.len()));
+                self.ships.push(ship);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the indexes of the given coordinates if a ship can be placed on them.
+    ///
+    /// Coordinates must be on the water and must not be occupied by other ships
+    /// than the ship with the given index.
+    fn free_indexes(&self, coords: &[Coordinate], ship: Option<usize>) -> Option<Vec<usize>> {
+        coords
             .iter()
-            .any(|s| s.coords.iter().any(|coord| ship.coords.contains(coord)));
-        let overflows = ship
+            .map(|coord| match self.tile(*coord) {
+                Tile::Water => self.index(*coord),
+                _ => None,
+            })
+            .collect::<Option<Vec<usize>>>()
+            .filter(|indexes| {
+                indexes
+                    .iter()
+                    .all(|i| self.cells[*i].is_none() || self.cells[*i] == ship)
+            })
+    }
+
+    /// Moves the ship at the given coordinate one cell towards the given direction.
+    ///
+    /// Only undamaged ships can be moved and the same checks with [`Grid::place_ship`] apply.
+    /// Returns `true` if the ship is moved.
+    pub fn move_ship(&mut self, coordinate: Coordinate, direction: Orientation) -> bool {
+        let ship = match self.index(coordinate).and_then(|i| self.cells[i]) {
+            Some(ship) if self.ships[ship].coords.iter().all(|c| !c.is_hit) => ship,
+            _ => return false,
+        };
+        let coords = match self.ships[ship]
             .coords
             .iter()
-            .any(|coord| coord.x > self.width || coord.y > self.height);
-        if overlaps || overflows {
-            false
+            .map(|coord| Self::step(*coord, direction))
+            .collect::<Option<Vec<Coordinate>>>()
+        {
+            Some(coords) => coords,
+            None => return false,
+        };
+        match self.free_indexes(&coords, Some(ship)) {
+            Some(indexes) => {
+                self.cells
+                    .iter_mut()
+                    .filter(|cell| **cell == Some(ship))
+                    .for_each(|cell| *cell = None);
+                indexes.into_iter().for_each(|i| self.cells[i] = Some(ship));
+                self.ships[ship].coords = coords;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Fires a shot at the given coordinate.
+    ///
+    /// Marks the ship part as hit or records the miss.
+    /// Shots at the blocked tiles are not recorded.
+    /// Returns the resulting state of the cell.
+    pub fn shoot(&mut self, coordinate: Coordinate) -> CellState {
+        let index = match self.index(coordinate) {
+            Some(index) if !self.tile(coordinate).is_blocked() => index,
+            _ => return CellState::Miss,
+        };
+        self.shots[index] = true;
+        if let Some(coord) = self.cells[index].and_then(|ship| {
+            self.ships[ship]
+                .coords
+                .iter_mut()
+                .find(|c| *c == &coordinate)
+        }) {
+            coord.is_hit = true;
+        }
+        self.cell_state(coordinate)
+    }
+
+    /// Returns the state of the cell at the given coordinate.
+    pub fn cell_state(&self, coordinate: Coordinate) -> CellState {
+        let index = match self.index(coordinate) {
+            Some(index) => index,
+            None => return CellState::Unknown,
+        };
+        match self.cells[index].map(|ship| &self.ships[ship]) {
+            Some(ship) if ship.is_sunk() => CellState::Sunk,
+            Some(ship)
+                if ship
+                    .coords
+                    .iter()
+                    .find(|c| *c == &coordinate)
+                    .map(|c| c.is_hit)
+                    == Some(true) =>
+            {
+                CellState::Hit
+            }
+            _ if self.shots[index] => CellState::Miss,
+            _ => match self.scans[index] {
+                Some(true) => CellState::Detected,
+                Some(false) => CellState::Clear,
+                None => CellState::Unknown,
+            },
+        }
+    }
+
+    /// Returns the coordinate at the given position if it is valid.
+    fn coordinate_at(x: i16, y: i16) -> Option<Coordinate> {
+        let range = 1..=i16::from(MAX_GRID_SIZE);
+        if range.contains(&x) && range.contains(&y) {
+            Some(Coordinate::from((x as u8, y as u8)))
         } else {
-            self.ships.push(ship);
-            true
+            None
+        }
+    }
+
+    /// Returns the adjacent coordinate towards the given direction if it is valid.
+    fn step(coordinate: Coordinate, direction: Orientation) -> Option<Coordinate> {
+        let (dx, dy) = match direction {
+            Orientation::North => (0, -1),
+            Orientation::East => (1, 0),
+            Orientation::South => (0, 1),
+            Orientation::West => (-1, 0),
+        };
+        Self::coordinate_at(i16::from(coordinate.x) + dx, i16::from(coordinate.y) + dy)
+    }
+
+    /// Returns the open water coordinates in the given area.
+    fn area(&self, x: i16, y: i16, width: u8, height: u8) -> Vec<Coordinate> {
+        (y..y + i16::from(height))
+            .flat_map(|y| (x..x + i16::from(width)).map(move |x| (x, y)))
+            .filter_map(|(x, y)| Self::coordinate_at(x, y))
+            .filter(|coord| !self.tile(*coord).is_blocked())
+            .collect()
+    }
+
+    /// Scans the [`SONAR_SIZE`] area around the given coordinate.
+    ///
+    /// Returns whether if there is any ship part that is not hit yet in the area.
+    /// The result is recorded for the cells in the area.
+    pub fn sonar(&mut self, coordinate: Coordinate) -> bool {
+        let offset = i16::from(SONAR_SIZE / 2);
+        let area = self.area(
+            i16::from(coordinate.x) - offset,
+            i16::from(coordinate.y) - offset,
+            SONAR_SIZE,
+            SONAR_SIZE,
+        );
+        let detected = area.iter().any(|coord| {
+            self.ship_at(*coord)
+                .and_then(|ship| ship.coords.iter().find(|c| *c == coord))
+                .map(|c| !c.is_hit)
+                == Some(true)
+        });
+        for coord in area {
+            if let Some(index) = self.index(coord) {
+                self.scans[index] = Some(detected);
+            }
         }
+        detected
+    }
+
+    /// Fires shots at the [`AIRSTRIKE_LENGTH`] row segment centered on the given coordinate.
+    ///
+    /// Returns the resulting states of the cells.
+    pub fn airstrike(&mut self, coordinate: Coordinate) -> Vec<(Coordinate, CellState)> {
+        self.area(
+            i16::from(coordinate.x) - i16::from(AIRSTRIKE_LENGTH / 2),
+            i16::from(coordinate.y),
+            AIRSTRIKE_LENGTH,
+            1,
+        )
+        .into_iter()
+        .map(|coord| (coord, self.shoot(coord)))
+        .collect()
+    }
+
+    /// Launches a torpedo from the given coordinate towards the given direction.
+    ///
+    /// Torpedo travels until it hits a ship, land or the edge of the grid.
+    /// Returns the resulting states of the cells that the torpedo passed.
+    pub fn torpedo(
+        &mut self,
+        coordinate: Coordinate,
+        direction: Orientation,
+    ) -> Vec<(Coordinate, CellState)> {
+        let mut results = Vec::new();
+        let mut coord = Some(coordinate);
+        while let Some(current) = coord.filter(|c| !self.tile(*c).is_blocked()) {
+            let is_ship = self.ship_at(current).is_some();
+            results.push((current, self.shoot(current)));
+            if is_ship {
+                break;
+            }
+            coord = Self::step(current, direction);
+        }
+        results
+    }
+
+    /// Returns whether if all the ships on the grid are sunk.
+    pub fn is_cleared(&self) -> bool {
+        self.ships.iter().all(|ship| ship.is_sunk())
     }
 
     /// Returns the grid as string.
     ///
-    /// Only hits/misses are shown if `show_ships` is true.
+    /// Ships are shown along with the hits/misses if `show_ships` is true.
+    /// Otherwise, only the [`CellState`]s are shown.
     pub fn as_string(&self, show_ships: bool) -> Result<String> {
         let mut s = Vec::new();
         self.display(&mut s, show_ships)?;
@@ -167,58 +659,41 @@ impl Grid {
     /// Display a point on the grid.
     ///
     /// The point might be empty or a part of a ship.
+    /// It is padded to the given width.
     fn display_point<W: Write>(
         &self,
         out: &mut W,
         coordinate: Coordinate,
         show_ships: bool,
+        width: usize,
     ) -> IoResult<()> {
-        if let Some(ship) = self
-            .ships
-            .iter()
-            .find(|ship| ship.coords.contains(&coordinate))
-        {
-            write!(
-                out,
-                "{} ",
-                if ship
-                    .coords
-                    .iter()
-                    .find(|c| *c == &coordinate)
-                    .map(|c| c.is_hit)
-                    == Some(true)
-                {
-                    HIT_POINT.to_string()
-                } else if show_ships {
-                    ship.type_.to_string()
-                } else {
-                    MISSED_POINT.to_string()
-                }
-            )?;
-        } else {
-            write!(out, "{} ", DEFAULT_POINT)?;
-        }
-        Ok(())
+        let state = self.cell_state(coordinate);
+        let point = match self.ship_at(coordinate) {
+            _ if self.tile(coordinate).is_blocked() => self.tile(coordinate).to_string(),
+            Some(ship) if show_ships && state != CellState::Hit && state != CellState::Sunk => {
+                ship.glyph.to_string()
+            }
+            _ => state.to_string(),
+        };
+        write!(out, "{:<width$} ", point, width = width)
     }
 
     /// Prints the grid to the given output.
+    ///
+    /// Columns are widened to fit the longest column name.
     fn display<W: Write>(&self, out: &mut W, show_ships: bool) -> IoResult<()> {
-        let alphabet_chars = ALPHABET.chars().collect::<Vec<char>>();
+        let column_width = column_name(self.width()).len();
+        let row_width = self.height().to_string().len().max(2);
         writeln!(out)?;
-        for h in 0..self.height + 1 {
-            if h == 0 {
-                write!(out, "   ")?;
-            } else if h.to_string().len() == 2 {
-                write!(out, "{} ", h)?;
-            } else {
-                write!(out, "{}  ", h)?;
-            }
-            for w in 0..self.width {
-                if h == 0 {
-                    write!(out, "{} ", alphabet_chars[w as usize].to_uppercase())?;
-                } else {
-                    self.display_point(out, Coordinate::from((w + 1, h)), show_ships)?;
-                }
+        write!(out, "{:width$} ", "", width = row_width)?;
+        for w in 1..=self.width() {
+            write!(out, "{:<width$} ", column_name(w), width = column_width)?;
+        }
+        writeln!(out)?;
+        for h in 1..=self.height() {
+            write!(out, "{:<width$} ", h, width = row_width)?;
+            for w in 1..=self.width() {
+                self.display_point(out, Coordinate::from((w, h)), show_ships, column_width)?;
             }
             writeln!(out)?;
         }
@@ -229,6 +704,7 @@ impl Grid {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shape::ShipDefinition;
     use crate::ship::Orientation;
 
     #[test]
@@ -240,15 +716,70 @@ mod tests {
             ((9, 1), "I1"),
             ((6, 8), "f8"),
             ((26, 2), "z2"),
+            ((27, 12), "AA12"),
+            ((53, 1), "ba1"),
+            ((255, 255), "IU255"),
         ];
         for (coord, coord_str) in test_cases {
             let coordinate = Coordinate::from(coord);
             assert_eq!(Ok(coordinate), Coordinate::try_from(coord_str.to_string()));
             assert_eq!(coord_str.to_uppercase(), coordinate.to_string())
         }
-        assert!(Coordinate::try_from(String::from("test")).is_err());
-        assert!(Coordinate::try_from(String::from("a999")).is_err());
-        assert!(Coordinate::try_from(String::from("42")).is_err());
+        for (coord, coord_str) in &[
+            ((1, 1), "a 1"),
+            ((1, 1), "1A"),
+            ((1, 1), "A-1"),
+            ((3, 12), " 12 - c "),
+            ((28, 3), "3ab"),
+            ((28, 3), "3-AB"),
+        ] {
+            assert_eq!(Ok(Coordinate::from(*coord)), coord_str.parse());
+            assert_eq!(
+                Ok(Coordinate::from(*coord)),
+                Coordinate::try_from(*coord_str)
+            );
+        }
+        for (coord_str, error) in vec![
+            ("", CoordinateParseError::Empty),
+            ("   ", CoordinateParseError::Empty),
+            ("test", CoordinateParseError::MissingRow),
+            ("42", CoordinateParseError::MissingColumn),
+            ("a-", CoordinateParseError::MissingRow),
+            (
+                "a999",
+                CoordinateParseError::RowOutOfRange(String::from("999")),
+            ),
+            ("a0", CoordinateParseError::RowOutOfRange(String::from("0"))),
+            (
+                "aab3",
+                CoordinateParseError::ColumnOutOfRange(String::from("aab")),
+            ),
+            (
+                "IV1",
+                CoordinateParseError::ColumnOutOfRange(String::from("IV")),
+            ),
+            ("a1b", CoordinateParseError::InvalidCharacter('b')),
+            ("a--1", CoordinateParseError::InvalidCharacter('-')),
+            ("a 1 2", CoordinateParseError::InvalidCharacter('2')),
+            ("#4", CoordinateParseError::InvalidCharacter('#')),
+            ("ç4", CoordinateParseError::InvalidCharacter('ç')),
+        ] {
+            assert_eq!(Err(error), Coordinate::from_str(coord_str));
+        }
+        assert_eq!(
+            "column AAB is out of range",
+            CoordinateParseError::ColumnOutOfRange(String::from("aab")).to_string()
+        );
+        assert_eq!("A", column_name(1));
+        assert_eq!("Z", column_name(26));
+        assert_eq!("AA", column_name(27));
+        assert_eq!("AZ", column_name(52));
+        assert_eq!("IU", column_name(255));
+        for i in 1..=MAX_GRID_SIZE {
+            assert_eq!(Some(i), column_index(&column_name(i)));
+        }
+        assert_eq!(None, column_index("zz"));
+        assert_eq!(None, column_index(""));
         assert_eq!(
             format!("{:?}", Coordinate::from((10, 2))),
             "Coordinate { x: 10, y: 2 }"
@@ -261,17 +792,18 @@ mod tests {
         assert!(grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((2, 3))])));
         assert!(grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((4, 5))])));
         assert!(grid.place_ship(Ship::new(
-            ShipType::Destroyer(Orientation::Horizontal),
-            vec![Coordinate::try_from(String::from("D1")).unwrap()]
+            ShipType::Destroyer(Orientation::East),
+            vec![Coordinate::try_from(String::from("D1")).expect("invalid coordinate")]
         )));
         assert!(!grid.place_ship(Ship::new(
-            ShipType::Battleship(Orientation::Vertical),
-            ShipType::Battleship(Orientation::Vertical).get_hitbox(Coordinate::from((2, 4)))
+            ShipType::Battleship(Orientation::North),
+            ShipType::Battleship(Orientation::North).get_hitbox(Coordinate::from((2, 4)))
         )));
         assert!(grid.place_ship(Ship::new(
             ShipType::Boat,
             vec![{
-                let mut coordinate = Coordinate::try_from(String::from("B5")).unwrap();
+                let mut coordinate =
+                    Coordinate::try_from(String::from("B5")).expect("invalid coordinate");
                 coordinate.is_hit = true;
                 coordinate
             }]
@@ -283,24 +815,251 @@ mod tests {
 2  • • • • • 
 3  • △ • • • 
 4  • • • • • 
-5  • ☒ • △ • 
+5  • ■ • △ • 
 "#,
             grid.as_string(true)?
         );
         assert_eq!(
             r#"
    A B C D E 
-1  • • • ✕ • 
+1  • • • • • 
 2  • • • • • 
-3  • ✕ • • • 
+3  • • • • • 
 4  • • • • • 
-5  • ☒ • ✕ • 
+5  • ■ • • • 
 "#,
             grid.as_string(false)?
         );
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::North),
+            ShipType::Destroyer(Orientation::North).get_hitbox(Coordinate::from((5, 2)))
+        )));
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((1, 1))));
+        assert_eq!(CellState::Sunk, grid.shoot(Coordinate::from((4, 1))));
+        assert_eq!(CellState::Hit, grid.shoot(Coordinate::from((5, 2))));
+        assert_eq!(
+            CellState::Unknown,
+            grid.cell_state(Coordinate::from((5, 3)))
+        );
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((1, 1))));
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((9, 9))));
+        assert_eq!(vec![Coordinate::from((1, 1))], grid.misses());
+        assert_eq!(
+            Some(ShipType::Boat),
+            grid.ship_at(Coordinate::from((2, 3)))
+                .map(|ship| ship.type_)
+        );
+        assert!(grid.ship_at(Coordinate::from((3, 3))).is_none());
+        assert_eq!(
+            r#"
+   A B C D E 
+1  ✕ • • ■ • 
+2  • • • • ☒ 
+3  • • • • • 
+4  • • • • • 
+5  • ■ • • • 
+"#,
+            grid.as_string(false)?
+        );
+        assert_eq!(
+            r#"
+   A B C D E 
+1  ✕ • • ■ • 
+2  • • • • ☒ 
+3  • △ • • ▯ 
+4  • • • • • 
+5  • ■ • △ • 
+"#,
+            grid.as_string(true)?
+        );
+        assert_eq!(CellState::Sunk, grid.shoot(Coordinate::from((5, 3))));
+        assert_eq!(CellState::Sunk, grid.cell_state(Coordinate::from((5, 2))));
 
         let grid = Grid::new_random(15, 15);
-        assert!(!grid.ships.is_empty());
+        assert!(!grid.ships().is_empty());
+
+        let mut grid = Grid::new(10, 10);
+        grid.place_random_ships(&Fleet::Custom(ShipDefinition::load_all(
+            "fleets/shapes.txt",
+        )?));
+        assert_eq!(4, grid.ships().len());
+        assert!(grid.as_string(true)?.contains('✚'));
+
+        let mut grid = Grid::new(10, 10);
+        grid.place_random_ships(&Fleet::Classic);
+        assert_eq!(5, grid.ships().len());
+        assert_eq!(
+            17,
+            grid.ships()
+                .iter()
+                .map(|ship| ship.coords.len())
+                .sum::<usize>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_map_grid() -> Result<()> {
+        let mut grid = Grid::from_map(Map::from_str("...\n.#.\n..")?);
+        assert!(!grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((2, 2))])));
+        assert!(!grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((3, 3))])));
+        assert!(!grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::North),
+            ShipType::Destroyer(Orientation::North).get_hitbox(Coordinate::from((2, 1)))
+        )));
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::North),
+            ShipType::Destroyer(Orientation::North).get_hitbox(Coordinate::from((1, 2)))
+        )));
+        assert!(grid.contains(Coordinate::from((2, 2))));
+        assert!(!grid.contains(Coordinate::from((3, 3))));
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((2, 2))));
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((3, 3))));
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((3, 2))));
+        assert_eq!(vec![Coordinate::from((3, 2))], grid.misses());
+        assert_eq!(
+            r#"
+   A B C 
+1  • • • 
+2  ▯ ▓ ✕ 
+3  ▯ •   
+"#,
+            grid.as_string(true)?
+        );
+
+        let mut grid = Grid::from_map(Map::from_str("#.\n##")?);
+        grid.place_random_ships(&Fleet::Orhun);
+        assert!(grid.ships().iter().all(|ship| ship.type_ == ShipType::Boat));
+        Ok(())
+    }
+
+    #[test]
+    fn test_weapons() -> Result<()> {
+        let mut grid = Grid::from_map(Map::from_str("......\n......\n...#..\n......")?);
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::North),
+            vec![Coordinate::from((2, 2)), Coordinate::from((2, 3))]
+        )));
+
+        assert!(grid.sonar(Coordinate::from((1, 1))));
+        assert_eq!(
+            CellState::Detected,
+            grid.cell_state(Coordinate::from((1, 1)))
+        );
+        assert_eq!(
+            CellState::Unknown,
+            grid.cell_state(Coordinate::from((3, 3)))
+        );
+        assert!(!grid.sonar(Coordinate::from((5, 3))));
+        assert_eq!(CellState::Clear, grid.cell_state(Coordinate::from((6, 4))));
+        let grid_str = grid.as_string(false)?;
+        assert!(grid_str.contains(&format!("{} ", DETECTED_POINT)));
+        assert!(grid_str.contains(&format!("{} ", CLEAR_POINT)));
+
+        let results = grid.airstrike(Coordinate::from((3, 3)));
+        assert_eq!(4, results.len());
+        assert_eq!((Coordinate::from((2, 3)), CellState::Hit), results[1]);
+        assert_eq!(
+            3,
+            results
+                .iter()
+                .filter(|(_, state)| *state == CellState::Miss)
+                .count()
+        );
+        assert!(grid.sonar(Coordinate::from((1, 1))));
+
+        let results = grid.torpedo(Coordinate::from((6, 2)), Orientation::West);
+        assert_eq!(5, results.len());
+        assert_eq!((Coordinate::from((2, 2)), CellState::Sunk), results[4]);
+        assert!(grid.is_cleared());
+        assert!(!grid.sonar(Coordinate::from((2, 2))));
+
+        let results = grid.torpedo(Coordinate::from((4, 1)), Orientation::South);
+        assert_eq!(vec![Coordinate::from((4, 1)), Coordinate::from((4, 2))], {
+            results.iter().map(|(c, _)| *c).collect::<Vec<Coordinate>>()
+        });
+        let results = grid.torpedo(Coordinate::from((6, 1)), Orientation::East);
+        assert_eq!(1, results.len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_ship() -> Result<()> {
+        let mut grid = Grid::from_map(Map::from_str("....\n.#..\n....")?);
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Destroyer(Orientation::East),
+            vec![Coordinate::from((3, 1)), Coordinate::from((4, 1))]
+        )));
+        assert!(grid.place_ship(Ship::new(ShipType::Boat, vec![Coordinate::from((1, 3))])));
+
+        // Boundaries.
+        assert!(!grid.move_ship(Coordinate::from((3, 1)), Orientation::North));
+        assert!(!grid.move_ship(Coordinate::from((4, 1)), Orientation::East));
+        assert!(!grid.move_ship(Coordinate::from((1, 3)), Orientation::West));
+        assert!(!grid.move_ship(Coordinate::from((1, 3)), Orientation::South));
+        // Empty cell.
+        assert!(!grid.move_ship(Coordinate::from((1, 1)), Orientation::East));
+
+        // Moving onto own cells.
+        assert!(grid.move_ship(Coordinate::from((4, 1)), Orientation::West));
+        assert!(grid.ship_at(Coordinate::from((4, 1))).is_none());
+        assert_eq!(
+            vec![Coordinate::from((2, 1)), Coordinate::from((3, 1))],
+            grid.ships()[0].coords
+        );
+        // Land.
+        assert!(!grid.move_ship(Coordinate::from((2, 1)), Orientation::South));
+        // Collision.
+        assert!(grid.move_ship(Coordinate::from((1, 3)), Orientation::North));
+        assert!(grid.move_ship(Coordinate::from((1, 2)), Orientation::North));
+        assert!(!grid.move_ship(Coordinate::from((2, 1)), Orientation::West));
+        assert!(!grid.move_ship(Coordinate::from((1, 1)), Orientation::East));
+
+        // Moved ships do not leak on the tracking grid.
+        assert_eq!(CellState::Miss, grid.shoot(Coordinate::from((4, 3))));
+        let tracking = grid.as_string(false)?;
+        assert!(grid.move_ship(Coordinate::from((3, 1)), Orientation::East));
+        assert!(grid.move_ship(Coordinate::from((1, 1)), Orientation::South));
+        assert!(grid.move_ship(Coordinate::from((1, 2)), Orientation::South));
+        for x in 1..=3 {
+            assert!(grid.move_ship(Coordinate::from((x, 3)), Orientation::East));
+        }
+        assert!(grid.ship_at(Coordinate::from((4, 3))).is_some());
+        assert_eq!(CellState::Miss, grid.cell_state(Coordinate::from((4, 3))));
+        assert_eq!(tracking, grid.as_string(false)?);
+
+        // Damaged ships cannot move.
+        assert_eq!(CellState::Hit, grid.shoot(Coordinate::from((3, 1))));
+        assert!(!grid.move_ship(Coordinate::from((4, 1)), Orientation::South));
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_grid() -> Result<()> {
+        let mut grid = Grid::new(28, 100);
+        assert!(grid.place_ship(Ship::new(
+            ShipType::Boat,
+            vec![Coordinate::try_from(String::from("AB100")).expect("invalid coordinate")]
+        )));
+        let grid_str = grid.as_string(true)?;
+        let lines = grid_str.lines().collect::<Vec<&str>>();
+        assert_eq!(
+            "    A  B  C  D  E  F  G  H  I  J  K  L  M  N  O  P  Q  R  S  T  U  V  W  X  Y  Z  AA AB ",
+            lines[1]
+        );
+        assert_eq!(
+            "1   •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  ",
+            lines[2]
+        );
+        assert_eq!(
+            "100 •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  •  △  ",
+            lines[101]
+        );
+
+        let grid = Grid::new_random(MAX_GRID_SIZE, MAX_GRID_SIZE);
+        let grid_str = grid.as_string(false)?;
+        assert_eq!(usize::from(MAX_GRID_SIZE) + 2, grid_str.lines().count());
+        assert!(grid_str.contains(" IU "));
         Ok(())
     }
 }
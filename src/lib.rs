@@ -2,18 +2,47 @@
 
 #![warn(missing_docs, clippy::unwrap_used)]
 
+pub mod account;
+pub mod admin;
+pub mod bot;
+pub mod cli;
+pub mod config;
 pub mod game;
 pub mod grid;
+pub mod limits;
+pub mod lobby;
+pub mod logger;
+pub mod map;
+pub mod metrics;
 pub mod player;
+pub mod pool;
+pub mod record;
+pub mod registry;
+pub mod replay;
+pub mod rules;
+pub mod shape;
 pub mod ship;
+pub mod stats;
+pub mod weapon;
 
+use crate::config::{Config, Settings};
 use crate::game::Game;
-use crate::grid::{Grid, ALPHABET};
+use crate::lobby::{Lobby, Server};
+use crate::logger::Context;
+use crate::metrics::METRICS;
 use crate::player::Player;
-use std::io::{Error as IoError, ErrorKind};
-use std::net::TcpListener;
+use crate::registry::{Registry, SHUTDOWN_MESSAGE};
+use std::io::ErrorKind;
+use std::net::{IpAddr, TcpListener};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
+
+#[cfg(unix)]
+use signal_hook::{
+    consts::{SIGHUP, SIGINT, SIGTERM},
+    iterator::Signals,
+};
 
 /// ASCII art for the banner.
 const BANNER: &str = r#"        _    _
@@ -23,79 +52,352 @@ const BANNER: &str = r#"        _    _
 ~'`~'`~'`~'`~'`~'`~'`~
 Welcome to Battleship!"#;
 
+/// Interval of checking for new connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Type alias for the standard [`Result`] type.
 ///
 /// See <https://doc.rust-lang.org/rust-by-example/error/multiple_error_types/boxing_errors.html>
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
-/// Runs the game.
-pub fn run(socket_addr: &str, grid_width: u8, grid_height: u8) -> Result<()> {
+/// Returns whether if the given secret is equal to the expected one.
+///
+/// Secrets are compared in constant time so that the time does not reveal
+/// how much of a guess is correct.
+pub fn secret_eq(secret: &str, expected: &str) -> bool {
+    secret.len() == expected.len()
+        && secret
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Reloads the settings with the given function.
+///
+/// Running games keep their settings and the new ones apply to the games that start afterwards.
+/// Settings are not changed if the configuration cannot be loaded.
+fn reload_settings(settings: &Mutex<Arc<Settings>>, reload: &dyn Fn() -> Result<Config>) {
+    log!(Info, "Reloading the configuration.");
+    let new_settings = match reload().and_then(Settings::load) {
+        Ok(new_settings) => new_settings,
+        Err(e) => {
+            log!(Error, "Failed to reload the configuration: {}", e);
+            return;
+        }
+    };
+    let mut settings = settings.lock().expect("failed to retrieve settings");
+    let changes = settings.changes(&new_settings);
+    if changes.is_empty() {
+        log!(Info, "Configuration is not changed.");
+    }
+    for change in changes {
+        log!(Info, "Changed {}", change);
+    }
+    for key in &[
+        "socket",
+        "accounts",
+        "metrics",
+        "admin_socket",
+        "admin_token",
+        "workers",
+    ] {
+        if settings.config.value(key) != new_settings.config.value(key) {
+            log!(Warn, "Changing {} requires a restart.", key);
+        }
+    }
+    logger::set_level(new_settings.config.log_level);
+    logger::set_format(new_settings.config.log_format);
+    *settings = Arc::new(new_settings);
+}
+
+/// Shuts down the server gracefully.
+///
+/// New connections are refused and the players that are not in a match are disconnected.
+/// Running matches are ended if they are not finished within the given time.
+fn shutdown(registry: &Arc<Registry>, timeout: Duration) {
+    log!(
+        Warn,
+        "Shutting down, running matches end in {} seconds.",
+        timeout.as_secs()
+    );
+    registry.drain(SHUTDOWN_MESSAGE);
+    registry.broadcast(&format!(
+        "\nServer shutting down. Running matches end in {} seconds.\n",
+        timeout.as_secs()
+    ));
+    let registry = Arc::clone(registry);
+    thread::spawn(move || {
+        thread::sleep(timeout);
+        let ended = registry.end_all(SHUTDOWN_MESSAGE);
+        if ended > 0 {
+            log!(
+                Warn,
+                "Ended {} match(es) that did not finish in time.",
+                ended
+            );
+        }
+    });
+}
+
+/// Returns the message for refusing a new connection from the given address.
+///
+/// Connections are refused if the address is banned or the connection limits are reached.
+fn check_limits(settings: &Settings, registry: &Registry, ip: IpAddr) -> Option<&'static str> {
+    let config = &settings.config;
+    if settings.bans.contains(ip) {
+        Some("Your address is banned.\n")
+    } else if config.max_connections != 0 && registry.count(None) >= config.max_connections {
+        Some("Server is full. Try again later.\n")
+    } else if config.max_connections_per_ip != 0
+        && registry.count(Some(ip)) >= config.max_connections_per_ip
+    {
+        Some("Too many connections from your address.\n")
+    } else {
+        None
+    }
+}
+
+/// Runs the game with the given [`Config`].
+///
+/// Grids of the players are built on the configured map
+/// and the ships are placed according to the configured rules.
+/// Records of the finished games are saved to the records directory if it is given.
+/// Connections during a running game join as spectators and the ones that know
+/// the admin password can see the ships of both players.
+/// Players can log in or register if the accounts file is given.
+///
+/// Connections from the banned addresses and the ones over the limits are refused.
+/// Names and passwords must be entered within the handshake timeout and the input
+/// of each connection is rate limited.
+/// Connections are handled by the [`Lobby`] with the configured number of workers
+/// until they join the game.
+///
+/// Operators can manage the server on the admin console if its address is given.
+/// The server stops when it is drained on the admin console and the running matches are finished.
+///
+/// Configuration is reloaded with the `reload` function on `SIGHUP` (on Unix).
+/// The server shuts down gracefully on `SIGINT` or `SIGTERM` (on Unix) and
+/// a second signal ends the running matches immediately.
+pub fn run<F>(config: Config, reload: F) -> Result<()>
+where
+    F: Fn() -> Result<Config> + Send + 'static,
+{
     // Prepare the game.
-    if usize::from(grid_width) > ALPHABET.len() || usize::from(grid_height) > ALPHABET.len() {
-        return Err("[!] Invalid grid dimensions.".into());
+    let settings = Settings::load(config)?;
+    let accounts = settings
+        .config
+        .accounts()?
+        .map(|accounts| Arc::new(Mutex::new(accounts)));
+    let socket_addr = settings.config.socket_addr.to_string();
+    if let Some(metrics_addr) = &settings.config.metrics_addr {
+        metrics::serve(metrics_addr)?;
+    }
+    let registry = Arc::new(Registry::default());
+    if let (Some(admin_socket), Some(admin_token)) =
+        (&settings.config.admin_socket, &settings.config.admin_token)
+    {
+        admin::serve(admin_socket, admin_token, Arc::clone(&registry))?;
     }
-    let game = Arc::new(Mutex::new(Game::default()));
+    let workers = settings.config.workers;
+    let settings = Arc::new(Mutex::new(Arc::new(settings)));
+    let spectators = Arc::new(Mutex::new(Vec::new()));
+    let game = Arc::new(Mutex::new(Game {
+        spectators: Arc::clone(&spectators),
+        registry: Arc::clone(&registry),
+        ..Game::default()
+    }));
+    let lobby = Lobby::start(
+        Server {
+            settings: Arc::clone(&settings),
+            accounts,
+            registry: Arc::clone(&registry),
+            game: Arc::clone(&game),
+            spectators,
+        },
+        workers,
+    )?;
 
     // Start listening for connections.
-    let listener = TcpListener::bind(socket_addr)?;
-    println!("[+] Server is listening on {}", socket_addr);
+    let listener = TcpListener::bind(&socket_addr)?;
+    // Accept without blocking for checking if the server is drained.
+    listener.set_nonblocking(true)?;
+    log!(Info, "Server is listening on {}", socket_addr);
+
+    // Reload the configuration on SIGHUP and shut down on SIGINT or SIGTERM.
+    #[cfg(unix)]
+    {
+        let settings = Arc::clone(&settings);
+        let registry = Arc::clone(&registry);
+        let mut signals = Signals::new([SIGHUP, SIGINT, SIGTERM])?;
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                if signal == SIGHUP {
+                    reload_settings(&settings, &reload);
+                } else if registry.is_draining() {
+                    log!(Warn, "Ending the running matches.");
+                    registry.end_all(SHUTDOWN_MESSAGE);
+                } else {
+                    let timeout = settings
+                        .lock()
+                        .expect("failed to retrieve settings")
+                        .config
+                        .shutdown_timeout;
+                    shutdown(&registry, timeout);
+                }
+            }
+        });
+    }
+    #[cfg(not(unix))]
+    drop(reload);
 
     // Handle connections.
     for stream in listener.incoming() {
+        if registry.is_drained() {
+            break;
+        }
         match stream {
             Ok(stream) => {
-                println!("[+] New connection: {}", stream.peer_addr()?);
+                METRICS.connection_accepted();
+                stream.set_nonblocking(false)?;
+                let peer_addr = stream.peer_addr()?;
                 let mut player = Player::new(stream);
-                if game.try_lock().is_err() {
-                    player.send("Lobby is full. Please wait.\n")?;
+                let player_id = player.id;
+                log!(
+                    Info,
+                    Context::player(player_id),
+                    "New connection: {}",
+                    peer_addr
+                );
+                if registry.is_draining() {
+                    let _ = player.send(SHUTDOWN_MESSAGE);
+                    continue;
+                }
+                let current_settings =
+                    Arc::clone(&settings.lock().expect("failed to retrieve settings"));
+                if let Some(message) = check_limits(&current_settings, &registry, peer_addr.ip()) {
+                    log!(
+                        Warn,
+                        Context::player(player_id),
+                        "Refused connection from {}: {}",
+                        peer_addr,
+                        message.trim_end()
+                    );
+                    let _ = player.send(message);
+                    continue;
+                }
+                player.set_rate_limit(current_settings.config.rate_limit);
+                if let Err(e) = player.register(Arc::clone(&registry)) {
+                    log!(
+                        Error,
+                        Context::player(player_id),
+                        "Failed to register connection: {}",
+                        e
+                    );
                     continue;
                 }
-                let game = Arc::clone(&game);
-                thread::spawn(move || {
-                    // Add a player to the game.
-                    let add_new_player = || -> Result<()> {
-                        player.greet()?;
-                        let mut game = game.lock().expect("failed to retrieve game");
-                        game.add_player(player)?;
-                        // Start the game when ready.
-                        if game.is_ready() {
-                            // Assign random boards to the players.
-                            game.players
-                                .iter_mut()
-                                .try_for_each::<_, Result<()>>(|player| {
-                                    player.grid = Grid::new_random(grid_width, grid_height);
-                                    println!(
-                                        "[#] {}'s grid:{}",
-                                        player.name,
-                                        player.grid.as_string(true)?
-                                    );
-                                    Ok(())
-                                })?;
-                            // Start the game loop.
-                            game.start(grid_width, grid_height)?;
-                        }
-                        Ok(())
-                    };
-
-                    // Handle errors.
-                    if let Err(e) = add_new_player() {
-                        eprintln!("[!] Gameplay error: {}", e);
-                        if let Ok(io_error) = e.downcast::<IoError>() {
-                            if io_error.kind() == ErrorKind::BrokenPipe {
-                                let mut game = game.lock().expect("failed to retrieve game");
-                                game.players.iter_mut().for_each(|player| {
-                                    let _ = player.send("Your opponent left the game.\n");
-                                });
-                                game.players.clear();
-                            }
-                        }
-                    }
-                });
+                // Connections join as spectators while a game is running.
+                let spectator = game.try_lock().is_err();
+                lobby.join(player, current_settings, spectator);
             }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
             Err(e) => {
-                eprintln!("[!] Connection failed: {}", e);
+                log!(Error, "Connection failed: {}", e);
             }
         }
     }
+    log!(Info, "Server is stopped.");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::Record;
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpStream;
+    use std::time::Instant;
+
+    #[test]
+    fn test_secret_eq() {
+        assert!(secret_eq("s3cret", "s3cret"));
+        assert!(!secret_eq("s3cre", "s3cret"));
+        assert!(!secret_eq("s3creT", "s3cret"));
+        assert!(!secret_eq("", "s3cret"));
+    }
+
+    #[test]
+    fn test_shutdown() -> Result<()> {
+        let records_dir =
+            env::temp_dir().join(format!("battleship-shutdown-{}", fastrand::u64(..)));
+        let config = Config {
+            countdown: 0,
+            records_dir: Some(records_dir.clone()),
+            ..Config::default()
+        };
+        let settings = Arc::new(Settings::load(config)?);
+        let registry = Arc::new(Registry::default());
+        let server = Server {
+            settings: Arc::new(Mutex::new(Arc::clone(&settings))),
+            accounts: None,
+            registry: Arc::clone(&registry),
+            game: Arc::new(Mutex::new(Game {
+                registry: Arc::clone(&registry),
+                ..Game::default()
+            })),
+            spectators: Arc::new(Mutex::new(Vec::new())),
+        };
+        let lobby = Lobby::start(server, 2)?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut clients = Vec::new();
+        for name in &["alice", "bob"] {
+            let mut client = TcpStream::connect(listener.local_addr()?)?;
+            let mut player = Player::new(listener.accept()?.0);
+            player.register(Arc::clone(&registry))?;
+            lobby.join(player, Arc::clone(&settings), false);
+            // Enter the name and join the game from the lobby.
+            client.write_all(format!("{}\n\n", name).as_bytes())?;
+            clients.push(client);
+        }
+        let start = Instant::now();
+        while registry.matches().is_empty() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "match is not started"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        // The match is not finished before the deadline, so it is ended and saved.
+        shutdown(&registry, Duration::from_millis(200));
+        assert!(registry.is_draining());
+        while !registry.is_drained() {
+            assert!(
+                start.elapsed() < Duration::from_secs(10),
+                "match is not ended"
+            );
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut records = fs::read_dir(&records_dir)?.collect::<std::io::Result<Vec<_>>>()?;
+        assert_eq!(1, records.len());
+        let record = Record::load(records.remove(0).path())?;
+        assert!(record.players.iter().any(|player| player.name == "alice"));
+        assert_eq!(None, record.winner);
+        for client in clients {
+            let lines = BufReader::new(client)
+                .lines()
+                .collect::<std::io::Result<Vec<String>>>()?;
+            assert!(lines
+                .iter()
+                .any(|line| line.contains("Running matches end in")));
+            assert_eq!(
+                Some(SHUTDOWN_MESSAGE.trim()),
+                lines.last().map(|line| line.trim())
+            );
+        }
+        fs::remove_dir_all(records_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+//! Limits against abusive connections.
+
+use crate::Result;
+use std::error::Error as StdError;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// The prefix of the comment lines in a ban list.
+const COMMENT_PREFIX: char = ';';
+
+/// Banned address or network.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Ban {
+    /// Address of the network.
+    address: IpAddr,
+    /// Length of the network prefix in bits.
+    prefix: u8,
+}
+
+impl FromStr for Ban {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let value = value.trim();
+        let (address, prefix) = match value.find('/') {
+            Some(index) => (&value[..index], Some(&value[index + 1..])),
+            None => (value, None),
+        };
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|e| format!("Invalid address: {} ({})", value, e))?;
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => match prefix.parse::<u8>() {
+                Ok(prefix) if prefix <= max_prefix => prefix,
+                _ => return Err(format!("Invalid prefix length: {}", value).into()),
+            },
+            None => max_prefix,
+        };
+        Ok(Self { address, prefix })
+    }
+}
+
+impl Ban {
+    /// Returns whether if the given address is in the banned network.
+    fn matches(&self, address: IpAddr) -> bool {
+        match (self.address, address) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// List of the banned addresses.
+///
+/// A ban list file consists of an IP address or a network in CIDR notation
+/// (e.g. `192.0.2.0/24`) on each line. Lines starting with `;` are ignored.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BanList {
+    /// Banned addresses and networks.
+    bans: Vec<Ban>,
+}
+
+impl FromStr for BanList {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let bans = value
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with(COMMENT_PREFIX))
+            .map(|(i, line)| {
+                Ban::from_str(line).map_err(|e| format!("line {}: {}", i + 1, e).into())
+            })
+            .collect::<Result<Vec<Ban>>>()?;
+        Ok(Self { bans })
+    }
+}
+
+impl BanList {
+    /// Loads the ban list from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(&path)?.parse().map_err(|e| {
+            format!(
+                "Failed to load ban list from {}: {}",
+                path.as_ref().display(),
+                e
+            )
+            .into()
+        })
+    }
+
+    /// Returns whether if the given address is banned.
+    pub fn contains(&self, address: IpAddr) -> bool {
+        self.bans.iter().any(|ban| ban.matches(address))
+    }
+}
+
+/// Token bucket for limiting the rate of the lines that are read from a connection.
+///
+/// Bursts up to the rate are allowed and the reads that exceed the rate are delayed.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    /// Number of the allowed lines per second.
+    rate: f64,
+    /// Number of the available lines, negative if the rate is exceeded.
+    tokens: f64,
+    /// Time of the last update.
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// Constructs a new instance of [`RateLimiter`] with the given number of lines per second.
+    pub fn new(lines_per_second: u32) -> Self {
+        let rate = f64::from(lines_per_second);
+        Self {
+            rate,
+            tokens: rate,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Returns the number of the available lines at the given time.
+    fn available(&self, now: Instant) -> f64 {
+        if now > self.updated {
+            let elapsed = (now - self.updated).as_secs_f64();
+            (self.tokens + elapsed * self.rate).min(self.rate)
+        } else {
+            self.tokens
+        }
+    }
+
+    /// Returns how long a reader should wait at the given time before taking a line.
+    pub fn delay(&self, now: Instant) -> Duration {
+        let available = self.available(now);
+        if available >= 1.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64((1.0 - available) / self.rate)
+        }
+    }
+
+    /// Takes a line at the given time and returns how long the reader should wait for it.
+    pub fn take(&mut self, now: Instant) -> Duration {
+        self.tokens = self.available(now);
+        self.updated = self.updated.max(now);
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::from_secs(0)
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn test_limits() -> Result<()> {
+        let bans = BanList::from_str(
+            "; Abusers
+            192.0.2.7
+            ; Spammers
+            198.51.100.0/24
+            2001:db8::/32",
+        )?;
+        let v4 = |a, b, c, d| IpAddr::V4(Ipv4Addr::new(a, b, c, d));
+        assert!(bans.contains(v4(192, 0, 2, 7)));
+        assert!(!bans.contains(v4(192, 0, 2, 8)));
+        assert!(bans.contains(v4(198, 51, 100, 200)));
+        assert!(!bans.contains(v4(198, 51, 101, 1)));
+        assert!(bans.contains(IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1))));
+        assert!(!bans.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert!(BanList::from_str("0.0.0.0/0")?.contains(v4(127, 0, 0, 1)));
+        for invalid in &["localhost", "192.0.2.0/33", "192.0.2.0/"] {
+            assert!(BanList::from_str(invalid).is_err(), "{} is parsed", invalid);
+        }
+
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(2);
+        assert_eq!(Duration::from_secs(0), limiter.take(start));
+        assert_eq!(Duration::from_secs(0), limiter.take(start));
+        assert_eq!(Duration::from_millis(500), limiter.delay(start));
+        assert_eq!(Duration::from_millis(500), limiter.take(start));
+        assert_eq!(Duration::from_secs(1), limiter.take(start));
+        let later = start + Duration::from_secs(10);
+        assert_eq!(Duration::from_secs(0), limiter.take(later));
+        Ok(())
+    }
+}
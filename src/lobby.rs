@@ -0,0 +1,984 @@
+//! Lobby of the server.
+//!
+//! Connections that are entering their names, logging in, waiting in the lobby or
+//! waiting for the running game to end are parked here without a thread.
+//! A single poller waits until they send a line and hands it to a worker of the
+//! [`ThreadPool`], so the idle connections only cost a socket. Games run in their own threads.
+
+use crate::account::{Account, Accounts};
+use crate::config::Settings;
+use crate::game::{Game, Spectator, MAX_PLAYERS};
+use crate::grid::Grid;
+use crate::logger::Context;
+use crate::metrics::{DisconnectReason, METRICS};
+use crate::player::Player;
+use crate::pool::ThreadPool;
+use crate::registry::{Registry, Status, SHUTDOWN_MESSAGE};
+use crate::stats::Stats;
+use crate::weapon::Ammo;
+use crate::{log, secret_eq, Result};
+use std::collections::VecDeque;
+use std::error::Error as StdError;
+use std::io::{Error as IoError, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::raw::c_int;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+/// Maximum time between the checks of the deadlines and the queued players.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// State of the server that is shared between the lobby and the games.
+#[derive(Clone, Debug)]
+pub struct Server {
+    /// Latest settings.
+    pub settings: Arc<Mutex<Arc<Settings>>>,
+    /// Player accounts.
+    pub accounts: Option<Arc<Mutex<Accounts>>>,
+    /// Registry of the connections and matches.
+    pub registry: Arc<Registry>,
+    /// Game that the players join.
+    pub game: Arc<Mutex<Game>>,
+    /// Spectators of the game.
+    pub spectators: Arc<Mutex<Vec<Spectator>>>,
+}
+
+/// Step of the handshake that a connection is at.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Connected as a player, not greeted yet.
+    Connected,
+    /// Connected as a spectator, not greeted yet.
+    Spectator,
+    /// Waiting for the name.
+    Name,
+    /// Waiting for the password of a registered name.
+    Password,
+    /// Waiting for a password to register the name.
+    Register,
+    /// Waiting for a lobby command.
+    Lobby,
+    /// Waiting for the admin password of a spectator.
+    AdminPassword,
+    /// Ready to play, waiting to join the game.
+    Queued,
+}
+
+/// Connection that is handled by the lobby.
+#[derive(Debug)]
+struct Connection {
+    /// Player of the connection.
+    player: Player,
+    /// Current step of the handshake.
+    stage: Stage,
+    /// Settings at the time of the connection.
+    settings: Arc<Settings>,
+}
+
+impl Connection {
+    /// Moves the connection to the lobby after the handshake.
+    ///
+    /// Shows the available lobby commands or queues the player if there are none.
+    fn enter_lobby(&mut self, server: &Server) -> Result<()> {
+        self.player.set_deadline(None)?;
+        self.stage = match self.lobby_prompt(server) {
+            Some(prompt) => {
+                self.player.send(&prompt)?;
+                Stage::Lobby
+            }
+            None => Stage::Queued,
+        };
+        Ok(())
+    }
+
+    /// Returns the prompt of the lobby if there are commands available.
+    ///
+    /// Ratings of the accounts are shown on `stats` command and
+    /// the statistics of the recorded games on `leaderboard` command.
+    fn lobby_prompt(&self, server: &Server) -> Option<String> {
+        let mut commands = Vec::new();
+        if server.accounts.is_some() {
+            commands.push("'stats' for the ratings");
+        }
+        if self.settings.config.records_dir.is_some() {
+            commands.push("'leaderboard' for the statistics");
+        }
+        if commands.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "Type {} or press enter to play: ",
+                commands.join(", ")
+            ))
+        }
+    }
+
+    /// Adds the spectator to the running game.
+    fn spectate(mut self, server: &Server, omniscient: bool) -> Result<()> {
+        self.player.set_deadline(None)?;
+        self.player
+            .send("Grids will be shown after the next turn.\n")?;
+        // Spectators that cannot keep up are dropped instead of holding up the game.
+        self.player.set_nonblocking(true)?;
+        server
+            .registry
+            .set_status(self.player.id, Status::Spectating);
+        server
+            .spectators
+            .lock()
+            .expect("failed to retrieve spectators")
+            .push(Spectator::new(self.player, omniscient));
+        Ok(())
+    }
+
+    /// Handles the given line, or greets the connection if it is new.
+    ///
+    /// Returns the connection if it stays in the lobby.
+    fn step(mut self, line: Option<String>, server: &Server) -> Result<Option<Self>> {
+        let line = line.unwrap_or_default();
+        match self.stage {
+            Stage::Connected => {
+                self.player
+                    .set_deadline(self.settings.config.handshake_timeout)?;
+                self.player.send(&format!(
+                    "{}\nPlease enter your name: ",
+                    self.settings.config.banner
+                ))?;
+                self.stage = Stage::Name;
+            }
+            Stage::Spectator => {
+                self.player
+                    .send("Lobby is full. Joining as a spectator.\n")?;
+                if self.settings.config.admin_password.is_none() {
+                    self.spectate(server, false)?;
+                    return Ok(None);
+                }
+                self.player
+                    .set_deadline(self.settings.config.handshake_timeout)?;
+                self.player
+                    .send("Enter the admin password to see all ships (or press enter): ")?;
+                self.stage = Stage::AdminPassword;
+            }
+            Stage::AdminPassword => {
+                let omniscient = self
+                    .settings
+                    .config
+                    .admin_password
+                    .as_ref()
+                    .map_or(false, |password| secret_eq(&line, password));
+                if omniscient {
+                    self.player.send("Omniscient mode is enabled.\n")?;
+                }
+                self.spectate(server, omniscient)?;
+                return Ok(None);
+            }
+            Stage::Name => {
+                self.player.set_name(&line);
+                let account_name = server.accounts.as_ref().and_then(|accounts| {
+                    accounts
+                        .lock()
+                        .expect("failed to retrieve accounts")
+                        .get(&self.player.name)
+                        .map(|account| account.name.clone())
+                });
+                // Registered names are reserved after logging in.
+                if let Some(name) = account_name {
+                    self.player.name = name;
+                    self.player.send("Password: ")?;
+                    self.stage = Stage::Password;
+                    return Ok(Some(self));
+                }
+                let accounts = server.accounts.as_ref();
+                self.player.reserve_name(|name| {
+                    accounts.map_or(false, |accounts| {
+                        accounts
+                            .lock()
+                            .expect("failed to retrieve accounts")
+                            .get(name)
+                            .is_some()
+                    })
+                });
+                if self.player.name != line {
+                    self.player
+                        .send(&format!("Your name is {}.\n", self.player.name))?;
+                }
+                if accounts.is_some() {
+                    self.player.send(
+                        "Enter a password to register (or press enter to play as a guest): ",
+                    )?;
+                    self.stage = Stage::Register;
+                } else {
+                    self.enter_lobby(server)?;
+                }
+            }
+            Stage::Password => {
+                let accounts = server.accounts.as_ref().ok_or("accounts are not enabled")?;
+                let account = accounts
+                    .lock()
+                    .expect("failed to retrieve accounts")
+                    .get(&self.player.name)
+                    .cloned();
+                // Hashing is slow by design, so the accounts are not locked meanwhile.
+                if !account.map_or(false, |account| account.verify(&line)) {
+                    self.player.send("Wrong password.\n")?;
+                    return Err(format!("failed login attempt for {}", self.player.name).into());
+                }
+                if !self.player.claim_name() {
+                    self.player
+                        .send(&format!("{} is already connected.\n", self.player.name))?;
+                    return Err(format!("{} is already connected", self.player.name).into());
+                }
+                self.player
+                    .send(&format!("Welcome back, {}!\n", self.player.name))?;
+                self.enter_lobby(server)?;
+            }
+            Stage::Register => {
+                if !line.is_empty() {
+                    let accounts = server.accounts.as_ref().ok_or("accounts are not enabled")?;
+                    let account = Account::new(&self.player.name, &line)?;
+                    accounts
+                        .lock()
+                        .expect("failed to retrieve accounts")
+                        .register(account)?;
+                    self.player
+                        .send(&format!("{} is registered.\n", self.player.name))?;
+                }
+                self.enter_lobby(server)?;
+            }
+            Stage::Lobby => {
+                match (
+                    line.to_lowercase().as_str(),
+                    &server.accounts,
+                    &self.settings.config.records_dir,
+                ) {
+                    ("", _, _) => {
+                        self.stage = Stage::Queued;
+                        return Ok(Some(self));
+                    }
+                    ("stats", Some(accounts), _) => {
+                        let leaderboard = accounts
+                            .lock()
+                            .expect("failed to retrieve accounts")
+                            .leaderboard();
+                        self.player.send(&leaderboard)?;
+                    }
+                    ("leaderboard", _, Some(records_dir)) => match Stats::load(records_dir) {
+                        Ok(stats) => self.player.send(&stats.as_string())?,
+                        Err(e) => {
+                            log!(
+                                Error,
+                                Context::player(self.player.id),
+                                "Failed to load statistics: {}",
+                                e
+                            );
+                            self.player.send("Statistics are not available.\n")?;
+                        }
+                    },
+                    (command, _, _) => self
+                        .player
+                        .send(&format!("Unknown command: {}\n", command))?,
+                }
+                if let Some(prompt) = self.lobby_prompt(server) {
+                    self.player.send(&prompt)?;
+                }
+            }
+            Stage::Queued => {}
+        }
+        Ok(Some(self))
+    }
+}
+
+/// Logs the error of a connection and counts the disconnect.
+fn report(context: Context, stage: Stage, error: &(dyn StdError + 'static)) {
+    match stage {
+        Stage::Spectator | Stage::AdminPassword => {
+            log!(Error, context, "Spectator error: {}", error)
+        }
+        _ => log!(Error, context, "Gameplay error: {}", error),
+    }
+    METRICS.disconnected(DisconnectReason::from_error(error));
+}
+
+/// Handles an error of the game.
+///
+/// Whatever the error is, one of the players cannot go on, so the others are told that
+/// their opponent left and the game is emptied for the next players.
+fn handle_game_error(game: &mut Game, context: Context, error: Box<dyn StdError>) {
+    report(context, Stage::Queued, error.as_ref());
+    game.players.iter_mut().for_each(|player| {
+        let _ = player.send("Your opponent left the game.\n");
+    });
+    game.players.clear();
+    METRICS.set_players_waiting(0);
+}
+
+/// Plays the game with the players that joined it.
+///
+/// Boards are assigned with the latest settings and the record of the game is saved,
+/// even if the game is abandoned.
+fn play(game: &mut Game, server: &Server) -> Result<()> {
+    // Use the latest settings for the new game.
+    let Settings {
+        config, map, rules, ..
+    } = server
+        .settings
+        .lock()
+        .expect("failed to retrieve settings")
+        .as_ref()
+        .clone();
+    // Assign random boards to the players.
+    game.players
+        .iter_mut()
+        .try_for_each::<_, Result<()>>(|player| {
+            player.grid = Grid::from_map(map.clone());
+            player.grid.place_random_ships(&rules.fleet);
+            player.ammo = Ammo::new(rules.mode);
+            if config.log_boards {
+                log!(
+                    Info,
+                    Context::player(player.id),
+                    "{}'s grid:{}",
+                    player.name,
+                    player.grid.as_string(true)?
+                );
+            }
+            Ok(())
+        })?;
+    // Start the game loop.
+    game.rules = rules.clone();
+    game.countdown = config.countdown;
+    game.turn_timeout = config.turn_timeout;
+    let result = game.start();
+    let record = &game.record;
+    if let (Some(accounts), Some(winner)) = (&server.accounts, record.winner) {
+        let loser = &record.players[MAX_PLAYERS - (winner + 1)].name;
+        if let Err(e) = accounts
+            .lock()
+            .expect("failed to retrieve accounts")
+            .record_game(&record.players[winner].name, loser)
+        {
+            log!(
+                Error,
+                Context::game(game.match_id),
+                "Failed to update accounts: {}",
+                e
+            );
+        }
+    }
+    if let Some(records_dir) = &config.records_dir {
+        match record.save(records_dir) {
+            Ok(path) => {
+                log!(
+                    Info,
+                    Context::game(game.match_id),
+                    "Game record is saved to {}",
+                    path.display()
+                )
+            }
+            Err(e) => log!(
+                Error,
+                Context::game(game.match_id),
+                "Failed to save game record: {}",
+                e
+            ),
+        }
+    }
+    // The server might stop once the match is ended, so the record is handled first.
+    game.registry.end_match(game.match_id);
+    result
+}
+
+/// Waits until one of the given file descriptors is readable or the time runs out.
+///
+/// Returns the readiness of each file descriptor.
+#[cfg(unix)]
+fn wait(fds: &[RawFd], timeout: Duration) -> Result<Vec<bool>> {
+    let mut fds = fds
+        .iter()
+        .map(|fd| libc::pollfd {
+            fd: *fd,
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect::<Vec<libc::pollfd>>();
+    // SAFETY: the pointer and the length are of a valid slice of `pollfd`s.
+    let result = unsafe {
+        libc::poll(
+            fds.as_mut_ptr(),
+            fds.len() as libc::nfds_t,
+            timeout.as_millis() as c_int,
+        )
+    };
+    if result < 0 {
+        let error = IoError::last_os_error();
+        if error.kind() != ErrorKind::Interrupted {
+            return Err(error.into());
+        }
+    }
+    Ok(fds.iter().map(|fd| fd.revents != 0).collect())
+}
+
+/// Handle for sending the connections to the poller.
+#[derive(Clone, Debug)]
+struct Handle {
+    /// Sender of the connections.
+    sender: Sender<Connection>,
+    /// Writer for waking up the poller.
+    #[cfg(unix)]
+    waker: Arc<UnixStream>,
+    /// Whether if the lobby is closed.
+    closed: Arc<AtomicBool>,
+}
+
+impl Handle {
+    /// Sends the connection to the poller.
+    fn park(&self, connection: Connection) {
+        let _ = self.sender.send(connection);
+        self.wake();
+    }
+
+    /// Wakes up the poller, e.g. for checking the queue again.
+    fn wake(&self) {
+        #[cfg(unix)]
+        {
+            let _ = (&*self.waker).write(&[1]);
+        }
+    }
+
+    /// Closes the lobby and stops the poller.
+    fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+        self.wake();
+    }
+}
+
+/// Poller of the connections in the lobby.
+struct Poller {
+    /// Shared state of the server.
+    server: Server,
+    /// Workers that handle the lines.
+    pool: ThreadPool,
+    /// Handle for parking the connections again.
+    handle: Handle,
+    /// Receiver of the connections.
+    receiver: Receiver<Connection>,
+    /// Reader of the wake-ups.
+    #[cfg(unix)]
+    waker: UnixStream,
+    /// Connections that are waiting for a line.
+    parked: Vec<Connection>,
+    /// Players that are waiting to join the game.
+    queue: VecDeque<Connection>,
+    /// Whether if players are being added to the game by a worker.
+    joining: Arc<AtomicBool>,
+}
+
+impl Poller {
+    /// Handles the given line of the connection in a worker.
+    fn dispatch(&self, connection: Connection, line: Option<String>) {
+        let (server, handle) = (self.server.clone(), self.handle.clone());
+        self.pool.execute(move || {
+            let (context, stage) = (Context::player(connection.player.id), connection.stage);
+            let result = connection
+                .player
+                .set_nonblocking(false)
+                .and_then(|_| connection.step(line, &server));
+            match result {
+                Ok(Some(connection)) => handle.park(connection),
+                Ok(None) => {}
+                Err(e) => report(context, stage, e.as_ref()),
+            }
+        });
+    }
+
+    /// Receives the connections that are sent to the poller.
+    ///
+    /// Returns `false` if the lobby is closed.
+    fn receive(&mut self) -> bool {
+        if self.handle.closed.load(Ordering::Relaxed) {
+            return false;
+        }
+        #[cfg(unix)]
+        {
+            let mut buffer = [0; 64];
+            while let Ok(size) = self.waker.read(&mut buffer) {
+                if size == 0 {
+                    break;
+                }
+            }
+        }
+        loop {
+            match self.receiver.try_recv() {
+                Ok(connection) => match connection.stage {
+                    Stage::Connected | Stage::Spectator => self.dispatch(connection, None),
+                    stage => match connection.player.set_nonblocking(true) {
+                        Ok(_) if stage == Stage::Queued => {
+                            self.server
+                                .registry
+                                .set_status(connection.player.id, Status::Waiting);
+                            self.queue.push_back(connection);
+                        }
+                        Ok(_) => self.parked.push(connection),
+                        Err(e) => report(
+                            Context::player(connection.player.id),
+                            connection.stage,
+                            e.as_ref(),
+                        ),
+                    },
+                },
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Adds the queued players to the game if it is not running.
+    ///
+    /// Players are added in a worker since they are informed about the opponent,
+    /// and the game is started in its own thread when it is ready.
+    fn join_game(&mut self) {
+        if self.server.registry.is_draining() {
+            for mut connection in self.queue.drain(..) {
+                let _ = connection.player.send(SHUTDOWN_MESSAGE);
+            }
+            return;
+        }
+        if self.queue.is_empty() || self.joining.load(Ordering::Relaxed) {
+            return;
+        }
+        let free = match self.server.game.try_lock() {
+            Ok(game) => MAX_PLAYERS.saturating_sub(game.players.len()),
+            Err(_) => return,
+        };
+        if free == 0 {
+            return;
+        }
+        let connections = self
+            .queue
+            .drain(..free.min(self.queue.len()))
+            .collect::<Vec<Connection>>();
+        self.joining.store(true, Ordering::Relaxed);
+        let (server, handle, joining) = (
+            self.server.clone(),
+            self.handle.clone(),
+            Arc::clone(&self.joining),
+        );
+        self.pool.execute(move || {
+            let mut game = match server.game.try_lock() {
+                Ok(game) => game,
+                Err(_) => {
+                    connections
+                        .into_iter()
+                        .for_each(|connection| handle.park(connection));
+                    joining.store(false, Ordering::Relaxed);
+                    return;
+                }
+            };
+            for connection in connections {
+                let context = Context::player(connection.player.id);
+                let player = connection.player;
+                if let Err(e) = player.set_nonblocking(false) {
+                    report(context, Stage::Queued, e.as_ref());
+                } else if let Err(e) = game.add_player(player) {
+                    handle_game_error(&mut game, context, e);
+                }
+            }
+            let is_ready = game.is_ready();
+            drop(game);
+            joining.store(false, Ordering::Relaxed);
+            handle.wake();
+            if is_ready {
+                thread::spawn(move || {
+                    let mut game = server.game.lock().expect("failed to retrieve game");
+                    if game.is_ready() {
+                        if let Err(e) = play(&mut game, &server) {
+                            let context = Context::game(game.match_id);
+                            handle_game_error(&mut game, context, e);
+                        }
+                    }
+                    drop(game);
+                    handle.wake();
+                });
+            }
+        });
+    }
+
+    /// Waits for the lines of the parked connections and dispatches them.
+    ///
+    /// Queued players are watched as well for noticing the ones that disconnect.
+    fn poll(&mut self) -> Result<()> {
+        let now = Instant::now();
+        let mut timeout = POLL_INTERVAL;
+        let mut watched = Vec::new();
+        for (i, connection) in self.parked.iter().enumerate() {
+            let throttle = connection.player.throttle(now);
+            if throttle > Duration::from_secs(0) {
+                timeout = timeout.min(throttle);
+            } else if connection.player.has_line() || connection.player.is_expired(now) {
+                timeout = Duration::from_secs(0);
+            } else {
+                watched.push(i);
+            }
+        }
+        let mut ready = vec![false; self.parked.len()];
+        let mut queue_ready = vec![false; self.queue.len()];
+        #[cfg(unix)]
+        {
+            let mut fds = vec![self.waker.as_raw_fd()];
+            fds.extend(
+                watched
+                    .iter()
+                    .map(|i| self.parked[*i].player.stream().as_raw_fd()),
+            );
+            fds.extend(
+                self.queue
+                    .iter()
+                    .map(|connection| connection.player.stream().as_raw_fd()),
+            );
+            let readiness = wait(&fds, timeout)?;
+            for (i, readable) in watched.iter().zip(&readiness[1..]) {
+                ready[*i] = *readable;
+            }
+            queue_ready.copy_from_slice(&readiness[1 + watched.len()..]);
+        }
+        // Without poll(2), the connections are checked once in a while.
+        #[cfg(not(unix))]
+        {
+            thread::sleep(timeout);
+            for i in watched {
+                ready[i] = true;
+            }
+            queue_ready = vec![true; self.queue.len()];
+        }
+        let now = Instant::now();
+        for (mut connection, readable) in self.parked.drain(..).zip(ready).collect::<Vec<_>>() {
+            let player = &connection.player;
+            if !(readable || player.has_line() || player.is_expired(now))
+                || player.throttle(now) > Duration::from_secs(0)
+            {
+                self.parked.push(connection);
+                continue;
+            }
+            match connection.player.try_read() {
+                Ok(Some(line)) => self.dispatch(connection, Some(line)),
+                Ok(None) => self.parked.push(connection),
+                Err(e) => report(
+                    Context::player(connection.player.id),
+                    connection.stage,
+                    e.as_ref(),
+                ),
+            }
+        }
+        for (mut connection, readable) in self.queue.drain(..).zip(queue_ready).collect::<Vec<_>>()
+        {
+            if readable {
+                // Lines that are sent while waiting are ignored.
+                let mut result = connection.player.try_read();
+                while let Ok(Some(_)) = result {
+                    result = connection.player.try_read();
+                }
+                if let Err(e) = result {
+                    report(
+                        Context::player(connection.player.id),
+                        connection.stage,
+                        e.as_ref(),
+                    );
+                    continue;
+                }
+            }
+            self.queue.push_back(connection);
+        }
+        Ok(())
+    }
+
+    /// Runs the poller until the lobby is closed.
+    fn run(mut self) {
+        while self.receive() {
+            self.join_game();
+            if let Err(e) = self.poll() {
+                log!(Error, "Failed to poll the connections: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Lobby that handles the new connections until they join the game.
+#[derive(Debug)]
+pub struct Lobby {
+    /// Handle of the poller.
+    handle: Handle,
+}
+
+impl Lobby {
+    /// Starts the poller with the given number of workers.
+    pub fn start(server: Server, workers: usize) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        #[cfg(unix)]
+        let (waker, waker_writer) = UnixStream::pair()?;
+        #[cfg(unix)]
+        {
+            waker.set_nonblocking(true)?;
+            waker_writer.set_nonblocking(true)?;
+        }
+        let handle = Handle {
+            sender,
+            #[cfg(unix)]
+            waker: Arc::new(waker_writer),
+            closed: Arc::new(AtomicBool::new(false)),
+        };
+        let poller = Poller {
+            server,
+            pool: ThreadPool::new(workers),
+            handle: handle.clone(),
+            receiver,
+            #[cfg(unix)]
+            waker,
+            parked: Vec::new(),
+            queue: VecDeque::new(),
+            joining: Arc::new(AtomicBool::new(false)),
+        };
+        thread::spawn(move || poller.run());
+        Ok(Self { handle })
+    }
+
+    /// Adds a new connection to the lobby.
+    ///
+    /// Players are greeted and the spectators join the running game after the handshake.
+    pub fn join(&self, player: Player, settings: Arc<Settings>, spectator: bool) {
+        self.handle.park(Connection {
+            player,
+            stage: if spectator {
+                Stage::Spectator
+            } else {
+                Stage::Connected
+            },
+            settings,
+        });
+    }
+}
+
+/// Stop the poller when the lobby goes out of scope.
+///
+/// Connections that are still in the lobby are closed.
+impl Drop for Lobby {
+    fn drop(&mut self) {
+        self.handle.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::env;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Read, Write};
+    #[cfg(unix)]
+    use std::mem;
+    use std::net::{TcpListener, TcpStream};
+
+    #[test]
+    fn test_lobby() -> Result<()> {
+        let config = Config {
+            banner: String::from("Ahoy!"),
+            ..Config::default()
+        };
+        let settings = Arc::new(Settings::load(config)?);
+        let server = Server {
+            settings: Arc::new(Mutex::new(Arc::clone(&settings))),
+            accounts: None,
+            registry: Arc::new(Registry::default()),
+            game: Arc::new(Mutex::new(Game::default())),
+            spectators: Arc::new(Mutex::new(Vec::new())),
+        };
+        let lobby = Lobby::start(server.clone(), 2)?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        player.register(Arc::clone(&server.registry))?;
+        lobby.join(player, Arc::clone(&settings), false);
+
+        let mut reader = BufReader::new(&client);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        assert_eq!("Ahoy!\n", line);
+        (&client).write_all(b"alice\n")?;
+        line.clear();
+        reader.read_line(&mut line)?;
+        assert_eq!("Please enter your name: Waiting for opponent...\n", line);
+        let players = server.registry.players();
+        assert_eq!("alice", players[0].name);
+        assert_eq!(Status::Waiting, players[0].status);
+        assert_eq!(
+            1,
+            server
+                .game
+                .lock()
+                .expect("failed to retrieve game")
+                .players
+                .len()
+        );
+
+        // Connections in the lobby are closed when it is dropped.
+        let client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        player.register(Arc::clone(&server.registry))?;
+        lobby.join(player, settings, false);
+        let mut reader = BufReader::new(&client);
+        line.clear();
+        reader.read_line(&mut line)?;
+        assert_eq!("Ahoy!\n", line);
+        drop(lobby);
+        client.set_read_timeout(Some(Duration::from_secs(5)))?;
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest)?;
+        assert_eq!("Please enter your name: ", rest);
+        assert_eq!(1, server.registry.players().len());
+        Ok(())
+    }
+
+    #[test]
+    fn test_login() -> Result<()> {
+        let config = Config {
+            banner: String::from("Ahoy!"),
+            ..Config::default()
+        };
+        let settings = Arc::new(Settings::load(config)?);
+        let path = env::temp_dir().join(format!("battleship-accounts-{}", fastrand::u64(..)));
+        let mut accounts = Accounts::load(&path)?;
+        accounts.register(Account::new("alice", "secret")?)?;
+        accounts.register(Account::new("bob-2", "secret")?)?;
+        let server = Server {
+            settings: Arc::new(Mutex::new(Arc::clone(&settings))),
+            accounts: Some(Arc::new(Mutex::new(accounts))),
+            registry: Arc::new(Registry::default()),
+            game: Arc::new(Mutex::new(Game::default())),
+            spectators: Arc::new(Mutex::new(Vec::new())),
+        };
+        let lobby = Lobby::start(server.clone(), 2)?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let connect = |name: &str| -> Result<BufReader<TcpStream>> {
+            let client = TcpStream::connect(listener.local_addr()?)?;
+            let mut player = Player::new(listener.accept()?.0);
+            player.register(Arc::clone(&server.registry))?;
+            lobby.join(player, Arc::clone(&settings), false);
+            (&client).write_all(format!("{}\n", name).as_bytes())?;
+            let mut reader = BufReader::new(client);
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            assert_eq!("Ahoy!\n", line);
+            Ok(reader)
+        };
+        let read_line = |reader: &mut BufReader<TcpStream>, input: &str| -> Result<String> {
+            reader.get_ref().write_all(input.as_bytes())?;
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            Ok(line)
+        };
+
+        // Registered names are not taken before logging in.
+        let mut impostor = connect("alice")?;
+        let mut alice = connect("\u{200b}ALICE")?;
+        assert_eq!(
+            "Please enter your name: Password: Welcome back, alice!\n",
+            read_line(&mut alice, "secret\n")?
+        );
+        assert_eq!(
+            "Please enter your name: Password: alice is already connected.\n",
+            read_line(&mut impostor, "secret\n")?
+        );
+
+        // Suffixes of the guest names skip the registered names.
+        let _bob = connect("bob")?;
+        let mut guest = connect("Bob")?;
+        assert_eq!(
+            "Please enter your name: Your name is Bob-3.\n",
+            read_line(&mut guest, "")?
+        );
+        drop(lobby);
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_game_error() -> Result<()> {
+        let config = Config {
+            banner: String::from("Ahoy!"),
+            countdown: 0,
+            ..Config::default()
+        };
+        let settings = Arc::new(Settings::load(config)?);
+        let server = Server {
+            settings: Arc::new(Mutex::new(Arc::clone(&settings))),
+            accounts: None,
+            registry: Arc::new(Registry::default()),
+            game: Arc::new(Mutex::new(Game::default())),
+            spectators: Arc::new(Mutex::new(Vec::new())),
+        };
+        let lobby = Lobby::start(server.clone(), 2)?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let connect = |name: &str| -> Result<BufReader<TcpStream>> {
+            let client = TcpStream::connect(listener.local_addr()?)?;
+            client.set_read_timeout(Some(Duration::from_secs(5)))?;
+            let mut player = Player::new(listener.accept()?.0);
+            player.register(Arc::clone(&server.registry))?;
+            lobby.join(player, Arc::clone(&settings), false);
+            (&client).write_all(format!("{}\n", name).as_bytes())?;
+            Ok(BufReader::new(client))
+        };
+        let read_until = |reader: &mut BufReader<TcpStream>, message: &str| -> Result<()> {
+            let mut line = String::new();
+            while !line.contains(message) {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Err(format!("{:?} is not received", message).into());
+                }
+            }
+            Ok(())
+        };
+
+        let mut alice = connect("alice")?;
+        let mut bob = connect("bob")?;
+        read_until(&mut alice, "Your opponent is bob")?;
+        read_until(&mut bob, "Your opponent is alice")?;
+
+        // Reset the connection instead of closing it gracefully.
+        let linger = libc::linger {
+            l_onoff: 1,
+            l_linger: 0,
+        };
+        // SAFETY: the file descriptor is open and the option value is a valid `linger`.
+        let result = unsafe {
+            libc::setsockopt(
+                alice.get_ref().as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_LINGER,
+                &linger as *const libc::linger as *const libc::c_void,
+                mem::size_of::<libc::linger>() as libc::socklen_t,
+            )
+        };
+        assert_eq!(0, result);
+        drop(alice);
+        bob.get_ref().write_all(b"A1\n")?;
+        read_until(&mut bob, "Your opponent left the game.")?;
+
+        // The next players are matched.
+        let mut carol = connect("carol")?;
+        let mut dave = connect("dave")?;
+        read_until(&mut carol, "Your opponent is dave")?;
+        read_until(&mut dave, "Your opponent is carol")?;
+        Ok(())
+    }
+}
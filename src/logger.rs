@@ -0,0 +1,330 @@
+//! Leveled logging.
+//!
+//! Each line has a timestamp, a level and the optional match and player IDs.
+//! Lines are written as plain text or JSON depending on the [`Format`].
+
+use crate::record;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fmt::Write as _;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Current log level.
+static LEVEL: AtomicUsize = AtomicUsize::new(Level::Info as usize);
+/// Whether if the lines are written as JSON.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Log levels from the most to the least important.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Failures.
+    Error,
+    /// Problems that do not stop the server.
+    Warn,
+    /// Connections and game events.
+    Info,
+    /// Details for debugging.
+    Debug,
+}
+
+/// Default level shows everything but the debug messages.
+impl Default for Level {
+    fn default() -> Self {
+        Self::Info
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Error => "error",
+                Self::Warn => "warn",
+                Self::Info => "info",
+                Self::Debug => "debug",
+            }
+        )
+    }
+}
+
+impl FromStr for Level {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            _ => Err(format!(
+                "Invalid log level: {} (expected error, warn, info or debug)",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+/// Output formats of the log lines.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Default format is text.
+impl Default for Format {
+    fn default() -> Self {
+        Self::Text
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Text => "text",
+                Self::Json => "json",
+            }
+        )
+    }
+}
+
+impl FromStr for Format {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Invalid log format: {} (expected text or json)", value).into()),
+        }
+    }
+}
+
+/// Context of a log line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    /// ID of the match.
+    pub match_id: Option<u64>,
+    /// ID of the player.
+    pub player_id: Option<u64>,
+}
+
+impl Context {
+    /// Constructs a new instance of [`Context`] for the given player.
+    pub fn player(player_id: u64) -> Self {
+        Self {
+            match_id: None,
+            player_id: Some(player_id),
+        }
+    }
+
+    /// Constructs a new instance of [`Context`] for the given match.
+    pub fn game(match_id: u64) -> Self {
+        Self {
+            match_id: Some(match_id),
+            player_id: None,
+        }
+    }
+
+    /// Returns the context with the given player.
+    pub fn with_player(self, player_id: u64) -> Self {
+        Self {
+            player_id: Some(player_id),
+            ..self
+        }
+    }
+}
+
+/// Sets the log level.
+pub fn set_level(level: Level) {
+    LEVEL.store(level as usize, Ordering::Relaxed);
+}
+
+/// Sets the output format.
+pub fn set_format(format: Format) {
+    JSON.store(format == Format::Json, Ordering::Relaxed);
+}
+
+/// Returns whether if the messages of the given level are logged.
+pub fn enabled(level: Level) -> bool {
+    level as usize <= LEVEL.load(Ordering::Relaxed)
+}
+
+/// Returns the given time (seconds since the Unix epoch) in RFC 3339 format (UTC).
+///
+/// See <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn format_time(time: u64) -> String {
+    let (days, seconds) = (time / 86400, time % 86400);
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Escapes the given value for a JSON string.
+fn json_string(value: &str) -> String {
+    let mut s = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(s, "\\u{:04x}", u32::from(c));
+            }
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+/// Returns the log line for the given message.
+pub fn format_line(
+    format: Format,
+    time: u64,
+    level: Level,
+    context: &Context,
+    message: &str,
+) -> String {
+    let time = format_time(time);
+    match format {
+        Format::Text => {
+            let mut line = format!("{} {:<5}", time, level.to_string().to_uppercase());
+            if let Some(match_id) = context.match_id {
+                let _ = write!(line, " match={}", match_id);
+            }
+            if let Some(player_id) = context.player_id {
+                let _ = write!(line, " player={}", player_id);
+            }
+            line + " " + message
+        }
+        Format::Json => {
+            let mut line = format!("{{\"time\":\"{}\",\"level\":\"{}\"", time, level);
+            if let Some(match_id) = context.match_id {
+                let _ = write!(line, ",\"match\":{}", match_id);
+            }
+            if let Some(player_id) = context.player_id {
+                let _ = write!(line, ",\"player\":{}", player_id);
+            }
+            let _ = write!(line, ",\"message\":{}}}", json_string(message));
+            line
+        }
+    }
+}
+
+/// Writes the message with the given level and context.
+///
+/// Errors and warnings are written to stderr, other messages to stdout.
+/// See the [`log`](crate::log) macro.
+pub fn write(level: Level, context: &Context, message: &str) {
+    let format = if JSON.load(Ordering::Relaxed) {
+        Format::Json
+    } else {
+        Format::Text
+    };
+    let line = format_line(format, record::now(), level, context, message);
+    match level {
+        Level::Error | Level::Warn => eprintln!("{}", line),
+        Level::Info | Level::Debug => println!("{}", line),
+    }
+}
+
+/// Logs the message if the given level is enabled.
+///
+/// A [`Context`] can be given before the message:
+///
+/// ```
+/// use battleship::log;
+/// use battleship::logger::Context;
+/// log!(Info, "Server is listening on {}", "127.0.0.1:1234");
+/// log!(Debug, Context::game(1).with_player(2), "Player {} is ready", 2);
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $message:literal $($arg:tt)*) => {
+        $crate::log!($level, $crate::logger::Context::default(), $message $($arg)*)
+    };
+    ($level:ident, $context:expr, $($arg:tt)*) => {
+        if $crate::logger::enabled($crate::logger::Level::$level) {
+            $crate::logger::write(
+                $crate::logger::Level::$level,
+                &$context,
+                &format!($($arg)*),
+            );
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logger() -> crate::Result<()> {
+        assert_eq!(Level::Warn, "WARN".parse()?);
+        assert_eq!("debug", Level::Debug.to_string());
+        assert!("verbose".parse::<Level>().is_err());
+        assert!(Level::Error < Level::Debug);
+        assert_eq!(Format::Json, "json".parse()?);
+
+        assert_eq!("1970-01-01T00:00:00Z", format_time(0));
+        assert_eq!("2024-02-29T13:37:42Z", format_time(1_709_213_862));
+        let context = Context::game(3).with_player(7);
+        assert_eq!(
+            "2024-02-29T13:37:42Z INFO  match=3 player=7 alice's turn",
+            format_line(
+                Format::Text,
+                1_709_213_862,
+                Level::Info,
+                &context,
+                "alice's turn"
+            )
+        );
+        assert_eq!(
+            "1970-01-01T00:00:00Z WARN  Skipping record",
+            format_line(
+                Format::Text,
+                0,
+                Level::Warn,
+                &Context::default(),
+                "Skipping record"
+            )
+        );
+        assert_eq!(
+            r#"{"time":"1970-01-01T00:00:00Z","level":"error","player":7,"message":"\"bob\"\n\u0007"}"#,
+            format_line(
+                Format::Json,
+                0,
+                Level::Error,
+                &Context::player(7),
+                "\"bob\"\n\u{7}"
+            )
+        );
+        Ok(())
+    }
+}
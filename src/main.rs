@@ -1,37 +1,209 @@
+use battleship::bot;
+use battleship::cli::{Cli, LeaderboardFormat, Subcommand, HELP};
+use battleship::config::{Config, Settings};
+use battleship::logger;
+use battleship::record::Record;
+use battleship::replay::Replay;
+use battleship::stats::Stats;
+use battleship::Result;
 use std::env;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::path::Path;
 use std::process;
 
-/// Default TCP socket address to serve the game.
-const DEFAULT_SOCKET_ADDR: &str = "127.0.0.1:1234";
-/// Environment variable for setting the TCP socket address.
-const SOCKET_ADDR_ENV: &str = concat!(env!("CARGO_PKG_NAME"), "_socket");
-/// Environment variable for setting the grid width.
-const GRID_WIDTH_ENV: &str = concat!(env!("CARGO_PKG_NAME"), "_grid_width");
-/// Environment variable for setting the grid height.
-const GRID_HEIGHT_ENV: &str = concat!(env!("CARGO_PKG_NAME"), "_grid_height");
+/// Replays the recorded game at the given path.
+fn replay(path: &Path, headless: bool) -> Result<()> {
+    let mut replay = Replay::new(Record::load(path)?);
+    if headless {
+        replay.finish();
+        print!("{}", replay.as_string()?);
+        Ok(())
+    } else {
+        let stdin = io::stdin();
+        replay.run(stdin.lock(), io::stdout())
+    }
+}
 
-fn main() {
-    // Get the TCP address from environment.
-    let socket_addr = env::var(SOCKET_ADDR_ENV.to_uppercase())
-        .unwrap_or_else(|_| DEFAULT_SOCKET_ADDR.to_string());
+/// Shows the statistics of the recorded games in the given directory.
+fn leaderboard(directory: &Path, format: LeaderboardFormat) -> Result<()> {
+    let stats = Stats::load(directory)?;
+    match format {
+        LeaderboardFormat::Table => print!("{}", stats.as_string()),
+        LeaderboardFormat::Csv => print!("{}", stats.players_csv()),
+        LeaderboardFormat::HeatMapCsv => print!("{}", stats.heat_map_csv()),
+    }
+    Ok(())
+}
 
-    // Get the game configuration from environment.
-    let (grid_width, grid_height) = (
-        env::var(GRID_WIDTH_ENV.to_uppercase())
-            .ok()
-            .and_then(|v| v.parse::<u8>().ok())
-            .unwrap_or(10),
-        env::var(GRID_HEIGHT_ENV.to_uppercase())
-            .ok()
-            .and_then(|v| v.parse::<u8>().ok())
-            .unwrap_or(10),
+/// Plays the given number of games between two bots and shows the results.
+fn selfplay(config: &Config, games: u32) -> Result<()> {
+    let Settings { map, rules, .. } = Settings::load(config.clone())?;
+    let mut wins = [0; 2];
+    let mut total_shots = 0;
+    for game in 1..=games {
+        let (winner, shots) = bot::selfplay(&map, &rules)
+            .ok_or("Bots ran out of shots before the game ended (is the map too small?)")?;
+        wins[winner] += 1;
+        total_shots += u64::from(shots);
+        println!("Game {}: Bot {} won in {} shots.", game, winner + 1, shots);
+    }
+    if games > 0 {
+        println!(
+            "Bot 1 won {}, Bot 2 won {} games. Average shots to win: {:.1}",
+            wins[0],
+            wins[1],
+            total_shots as f64 / f64::from(games)
+        );
+    }
+    Ok(())
+}
+
+/// Validates the configuration and shows the resolved config.
+fn check_config(config: &Config) -> Result<()> {
+    let addresses = config
+        .socket_addr
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid socket address: {} ({})", config.socket_addr, e))?;
+    let Settings { map, rules, .. } = Settings::load(config.clone())?;
+    config.accounts()?;
+    let disabled = || String::from("disabled");
+    let limit = |limit: usize| match limit {
+        0 => String::from("unlimited"),
+        limit => limit.to_string(),
+    };
+    println!(
+        "Socket: {}",
+        addresses
+            .map(|a| a.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    println!(
+        "Map: {} ({}x{})",
+        config
+            .map
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| String::from("rectangular")),
+        map.width(),
+        map.height()
+    );
+    println!("Fleet: {}", rules.fleet);
+    println!("Mode: {}", rules.mode);
+    println!(
+        "Turn timeout: {}",
+        config
+            .turn_timeout
+            .map(|timeout| format!("{}s", timeout.as_secs()))
+            .unwrap_or_else(disabled)
+    );
+    println!("Countdown: {}s", config.countdown);
+    println!("Log level: {}", config.log_level);
+    println!("Log format: {}", config.log_format);
+    println!(
+        "Board dumps: {}",
+        if config.log_boards {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+    println!(
+        "Records: {}",
+        config
+            .records_dir
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(disabled)
+    );
+    println!(
+        "Accounts: {}",
+        config
+            .accounts
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(disabled)
     );
+    println!(
+        "Admin password: {}",
+        if config.admin_password.is_some() {
+            "set"
+        } else {
+            "not set"
+        }
+    );
+    println!(
+        "Connection limits: {} total, {} per address",
+        limit(config.max_connections),
+        limit(config.max_connections_per_ip)
+    );
+    println!(
+        "Handshake timeout: {}",
+        config
+            .handshake_timeout
+            .map(|timeout| format!("{}s", timeout.as_secs()))
+            .unwrap_or_else(disabled)
+    );
+    println!(
+        "Rate limit: {}",
+        match config.rate_limit {
+            0 => disabled(),
+            rate_limit => format!("{} lines per second", rate_limit),
+        }
+    );
+    println!(
+        "Ban list: {}",
+        config
+            .bans
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(disabled)
+    );
+    println!("Workers: {}", config.workers);
+    println!(
+        "Admin console: {}",
+        config.admin_socket.clone().unwrap_or_else(disabled)
+    );
+    println!("Configuration is valid.");
+    Ok(())
+}
 
-    // Run the game.
-    match battleship::run(&socket_addr, grid_width, grid_height) {
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let cli = match Cli::parse(&args, |name| env::var(name).ok()) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("[!] {}", e);
+            process::exit(2)
+        }
+    };
+    logger::set_level(cli.config.log_level);
+    logger::set_format(cli.config.log_format);
+    let result = match cli.subcommand {
+        Subcommand::Serve => battleship::run(cli.config, move || {
+            Cli::parse(&args, |name| env::var(name).ok()).map(|cli| cli.config)
+        }),
+        Subcommand::Replay { path, headless } => replay(&path, headless),
+        Subcommand::Leaderboard { directory, format } => leaderboard(&directory, format),
+        Subcommand::Bot { address, name } => bot::play(&address, &name).map(|result| {
+            println!("{}", result);
+        }),
+        Subcommand::Selfplay { games } => selfplay(&cli.config, games),
+        Subcommand::CheckConfig => check_config(&cli.config),
+        Subcommand::Help => {
+            print!("{}", HELP);
+            Ok(())
+        }
+        Subcommand::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Ok(())
+        }
+    };
+    match result {
         Ok(_) => process::exit(0),
         Err(e) => {
-            eprintln!("{}", e);
+            eprintln!("[!] {}", e);
             process::exit(1)
         }
     }
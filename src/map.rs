@@ -0,0 +1,235 @@
+//! Game map.
+
+use crate::grid::{Coordinate, MAX_GRID_SIZE};
+use crate::Result;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+/// The character that represents water in a map file.
+const WATER_CHAR: char = '.';
+/// The character that represents land in a map file.
+const LAND_CHAR: char = '#';
+/// The character that represents a cell outside of the map in a map file.
+const VOID_CHAR: char = ' ';
+/// The prefix of the comment lines in a map file.
+const COMMENT_PREFIX: char = ';';
+/// The character that represents land on the grid.
+const LAND_POINT: &str = "▓";
+/// The character that represents a cell outside of the map on the grid.
+const VOID_POINT: &str = " ";
+
+/// Available tiles on the map.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    /// Open water where ships can be placed and shots can land.
+    Water,
+    /// Land/island that blocks ships and shots.
+    Land,
+    /// Cell that is outside of the map outline.
+    Void,
+}
+
+/// Default tile is water since the grid is a sea.
+impl Default for Tile {
+    fn default() -> Self {
+        Self::Water
+    }
+}
+
+/// Display the blocked tiles as a string.
+///
+/// Water is displayed by the grid based on its state.
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Tile::Water => "",
+                Tile::Land => LAND_POINT,
+                Tile::Void => VOID_POINT,
+            }
+        )
+    }
+}
+
+impl Tile {
+    /// Returns whether if the tile blocks ships and shots.
+    pub fn is_blocked(&self) -> bool {
+        *self != Tile::Water
+    }
+}
+
+/// Representation of the map that the grid is built on.
+///
+/// A map file consists of rows of tiles where `.` is water, `#` is land
+/// and ` ` (or a missing character at the end of the row) is outside of the map.
+/// Lines starting with `;` are ignored.
+///
+/// ```text
+/// ; Two islands in the middle
+/// ..........
+/// ..........
+/// ...##.....
+/// ...##..#..
+/// .......#..
+///  ........
+///   ......
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Map {
+    /// Width.
+    width: u8,
+    /// Height.
+    height: u8,
+    /// Tiles of the map in row-major order.
+    tiles: Vec<Tile>,
+}
+
+impl Map {
+    /// Constructs a new rectangular [`Map`] that consists of water.
+    pub fn new(width: u8, height: u8) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![Tile::Water; usize::from(width) * usize::from(height)],
+        }
+    }
+
+    /// Loads the map from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(&path)?.parse().map_err(|e| {
+            format!("Failed to load map from {}: {}", path.as_ref().display(), e).into()
+        })
+    }
+
+    /// Returns the width of the map.
+    pub fn width(&self) -> u8 {
+        self.width
+    }
+
+    /// Returns the height of the map.
+    pub fn height(&self) -> u8 {
+        self.height
+    }
+
+    /// Returns the tiles of the map in row-major order.
+    pub fn tiles(&self) -> &[Tile] {
+        &self.tiles
+    }
+
+    /// Returns the tile at the given coordinate.
+    ///
+    /// Coordinates outside of the map are [`Tile::Void`].
+    pub fn tile(&self, coordinate: Coordinate) -> Tile {
+        if (1..=self.width).contains(&coordinate.x) && (1..=self.height).contains(&coordinate.y) {
+            self.tiles[usize::from(coordinate.y - 1) * usize::from(self.width)
+                + usize::from(coordinate.x - 1)]
+        } else {
+            Tile::Void
+        }
+    }
+}
+
+/// Display the map in the map file format.
+///
+/// Cells outside of the map at the end of the rows are omitted.
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, row) in self
+            .tiles
+            .chunks(usize::from(self.width).max(1))
+            .enumerate()
+        {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            let row = row
+                .iter()
+                .map(|tile| match tile {
+                    Tile::Water => WATER_CHAR,
+                    Tile::Land => LAND_CHAR,
+                    Tile::Void => VOID_CHAR,
+                })
+                .collect::<String>();
+            write!(f, "{}", row.trim_end_matches(VOID_CHAR))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the map from the contents of a map file.
+impl FromStr for Map {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let rows = value
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.starts_with(COMMENT_PREFIX))
+            .collect::<Vec<&str>>();
+        let rows = match rows.iter().rposition(|row| !row.trim().is_empty()) {
+            Some(last) => &rows[..=last],
+            None => return Err("map is empty".into()),
+        };
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        if width > usize::from(MAX_GRID_SIZE) || rows.len() > usize::from(MAX_GRID_SIZE) {
+            return Err(format!("map is larger than {0}x{0}", MAX_GRID_SIZE).into());
+        }
+        let mut tiles = Vec::new();
+        for (i, row) in rows.iter().enumerate() {
+            for c in row.chars() {
+                tiles.push(match c {
+                    WATER_CHAR => Tile::Water,
+                    LAND_CHAR => Tile::Land,
+                    VOID_CHAR => Tile::Void,
+                    _ => {
+                        return Err(format!("unexpected character '{}' at line {}", c, i + 1).into())
+                    }
+                });
+            }
+            tiles.extend(vec![Tile::Void; width - row.chars().count()]);
+        }
+        if !tiles.contains(&Tile::Water) {
+            return Err("map does not contain any water".into());
+        }
+        Ok(Self {
+            width: width as u8,
+            height: rows.len() as u8,
+            tiles,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map() -> Result<()> {
+        let map = Map::from_str("; comment\n..#\n. \n.\n\n")?;
+        assert_eq!((3, 3), (map.width(), map.height()));
+        assert_eq!(Tile::Water, map.tile(Coordinate::from((1, 1))));
+        assert_eq!(Tile::Land, map.tile(Coordinate::from((3, 1))));
+        assert_eq!(Tile::Void, map.tile(Coordinate::from((2, 2))));
+        assert_eq!(Tile::Void, map.tile(Coordinate::from((3, 3))));
+        assert_eq!(Tile::Void, map.tile(Coordinate::from((4, 1))));
+        assert!(Tile::Land.is_blocked());
+        assert!(!Tile::Water.is_blocked());
+        assert_eq!(Map::new(2, 2), Map::from_str("..\n..")?);
+        assert_eq!("..#\n.\n.", map.to_string());
+        assert_eq!(map, map.to_string().parse()?);
+        assert!(Map::from_str("").is_err());
+        assert!(Map::from_str("##\n# ").is_err());
+        assert!(Map::from_str("..\n.x").is_err());
+        assert!(Map::from_str(&".".repeat(256)).is_err());
+        Ok(())
+    }
+}
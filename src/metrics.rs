@@ -0,0 +1,373 @@
+//! Prometheus-style metrics.
+//!
+//! See <https://prometheus.io/docs/instrumenting/exposition_formats/>
+
+use crate::logger::Context;
+use crate::{log, Result};
+use std::fmt::Write as _;
+use std::io::{Error as IoError, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Metrics of the server.
+pub static METRICS: Metrics = Metrics::new();
+
+/// Time limit of receiving a request and sending the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+/// Maximum size of the request line and the headers in bytes.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Reasons of the unexpected disconnects.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Connection is closed by the other side.
+    BrokenPipe,
+    /// Connection is reset or aborted.
+    ConnectionReset,
+    /// Connection has timed out.
+    Timeout,
+    /// Other I/O errors.
+    IoError,
+    /// Errors of the game, e.g. a failed login.
+    GameError,
+}
+
+impl DisconnectReason {
+    /// All the reasons in the order of the counters.
+    const ALL: [Self; 5] = [
+        Self::BrokenPipe,
+        Self::ConnectionReset,
+        Self::Timeout,
+        Self::IoError,
+        Self::GameError,
+    ];
+
+    /// Returns the reason of the disconnect that is caused by the given error.
+    pub fn from_error(error: &(dyn std::error::Error + 'static)) -> Self {
+        match error.downcast_ref::<IoError>().map(IoError::kind) {
+            Some(ErrorKind::BrokenPipe) => Self::BrokenPipe,
+            Some(ErrorKind::ConnectionReset) | Some(ErrorKind::ConnectionAborted) => {
+                Self::ConnectionReset
+            }
+            Some(ErrorKind::TimedOut) | Some(ErrorKind::WouldBlock) => Self::Timeout,
+            Some(_) => Self::IoError,
+            None => Self::GameError,
+        }
+    }
+
+    /// Returns the label value of the reason.
+    fn label(self) -> &'static str {
+        match self {
+            Self::BrokenPipe => "broken_pipe",
+            Self::ConnectionReset => "connection_reset",
+            Self::Timeout => "timeout",
+            Self::IoError => "io_error",
+            Self::GameError => "game_error",
+        }
+    }
+}
+
+/// Counters and gauges of the server.
+#[derive(Debug)]
+pub struct Metrics {
+    /// Number of the accepted connections.
+    connections_accepted: AtomicU64,
+    /// Number of the running matches.
+    active_matches: AtomicU64,
+    /// Number of the players in the lobby that are waiting for an opponent.
+    players_waiting: AtomicU64,
+    /// Number of the games that are finished with a winner.
+    games_completed: AtomicU64,
+    /// Total duration of the completed games in milliseconds.
+    game_duration_millis: AtomicU64,
+    /// Number of the shots that are fired.
+    shots_fired: AtomicU64,
+    /// Number of the disconnects for each [`DisconnectReason`].
+    disconnects: [AtomicU64; 5],
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Constructs a new instance of [`Metrics`] with zero values.
+    pub const fn new() -> Self {
+        Self {
+            connections_accepted: AtomicU64::new(0),
+            active_matches: AtomicU64::new(0),
+            players_waiting: AtomicU64::new(0),
+            games_completed: AtomicU64::new(0),
+            game_duration_millis: AtomicU64::new(0),
+            shots_fired: AtomicU64::new(0),
+            disconnects: [
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+                AtomicU64::new(0),
+            ],
+        }
+    }
+
+    /// Counts an accepted connection.
+    pub fn connection_accepted(&self) {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a started match.
+    pub fn match_started(&self) {
+        self.active_matches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a match that is ended without a winner.
+    pub fn match_aborted(&self) {
+        self.active_matches.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Counts a match that is completed in the given duration.
+    pub fn match_completed(&self, duration: Duration) {
+        self.active_matches.fetch_sub(1, Ordering::Relaxed);
+        self.games_completed.fetch_add(1, Ordering::Relaxed);
+        self.game_duration_millis
+            .fetch_add(duration.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Sets the number of the players that are waiting for an opponent.
+    pub fn set_players_waiting(&self, players: usize) {
+        self.players_waiting
+            .store(players as u64, Ordering::Relaxed);
+    }
+
+    /// Counts a fired shot.
+    pub fn shot_fired(&self) {
+        self.shots_fired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a disconnect with the given reason.
+    pub fn disconnected(&self, reason: DisconnectReason) {
+        if let Some(index) = DisconnectReason::ALL.iter().position(|r| *r == reason) {
+            self.disconnects[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut s = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            let _ = writeln!(s, "# HELP {} {}", name, help);
+            let _ = writeln!(s, "# TYPE {} {}", name, kind);
+            let _ = writeln!(s, "{} {}", name, value);
+        };
+        let load = |value: &AtomicU64| value.load(Ordering::Relaxed);
+        let games_completed = load(&self.games_completed);
+        let duration = load(&self.game_duration_millis) as f64 / 1000.0;
+        metric(
+            "battleship_connections_accepted_total",
+            "counter",
+            "Number of the accepted connections.",
+            load(&self.connections_accepted).to_string(),
+        );
+        metric(
+            "battleship_active_matches",
+            "gauge",
+            "Number of the running matches.",
+            load(&self.active_matches).to_string(),
+        );
+        metric(
+            "battleship_players_waiting",
+            "gauge",
+            "Number of the players that are waiting for an opponent.",
+            load(&self.players_waiting).to_string(),
+        );
+        metric(
+            "battleship_games_completed_total",
+            "counter",
+            "Number of the games that are finished with a winner.",
+            games_completed.to_string(),
+        );
+        metric(
+            "battleship_shots_fired_total",
+            "counter",
+            "Number of the shots that are fired.",
+            load(&self.shots_fired).to_string(),
+        );
+        metric(
+            "battleship_game_duration_seconds_sum",
+            "counter",
+            "Total duration of the completed games.",
+            format!("{:.3}", duration),
+        );
+        metric(
+            "battleship_game_duration_seconds_average",
+            "gauge",
+            "Average duration of the completed games.",
+            format!(
+                "{:.3}",
+                if games_completed == 0 {
+                    0.0
+                } else {
+                    duration / games_completed as f64
+                }
+            ),
+        );
+        let name = "battleship_disconnects_total";
+        let _ = writeln!(s, "# HELP {} Number of the unexpected disconnects.", name);
+        let _ = writeln!(s, "# TYPE {} counter", name);
+        for (reason, count) in DisconnectReason::ALL.iter().zip(&self.disconnects) {
+            let _ = writeln!(
+                s,
+                "{}{{reason=\"{}\"}} {}",
+                name,
+                reason.label(),
+                load(count)
+            );
+        }
+        s
+    }
+}
+
+/// Reads the request line and the headers of an HTTP request.
+///
+/// Fails if the request is not received in [`REQUEST_TIMEOUT`] or
+/// it is longer than [`MAX_REQUEST_SIZE`].
+fn read_request(mut stream: &TcpStream) -> Result<String> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(2).any(|bytes| bytes == b"\n\n")
+        && !request.windows(4).any(|bytes| bytes == b"\r\n\r\n")
+    {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err("request timed out".into());
+        }
+        stream.set_read_timeout(Some(deadline - now))?;
+        let size = stream.read(&mut buffer)?;
+        if size == 0 {
+            break;
+        }
+        request.extend_from_slice(&buffer[..size]);
+        if request.len() > MAX_REQUEST_SIZE {
+            return Err("request is too large".into());
+        }
+    }
+    Ok(String::from_utf8_lossy(&request).to_string())
+}
+
+/// Responds to an HTTP request with the metrics.
+fn respond(stream: TcpStream) -> Result<()> {
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let request = read_request(&stream)?;
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", METRICS.render()),
+        _ => ("404 Not Found", String::from("Not found.\n")),
+    };
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    Ok(())
+}
+
+/// Serves the metrics at `/metrics` on the given address in a new thread.
+///
+/// Requests are handled one at a time, each within [`REQUEST_TIMEOUT`].
+/// Returns the address that the metrics are served on.
+pub fn serve(address: &str) -> Result<SocketAddr> {
+    let listener = TcpListener::bind(address)?;
+    let address = listener.local_addr()?;
+    log!(Info, "Metrics are served on http://{}/metrics", address);
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(e) = stream.map_err(|e| e.into()).and_then(respond) {
+                log!(Warn, Context::default(), "Metrics request failed: {}", e);
+            }
+        }
+    });
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+        metrics.connection_accepted();
+        metrics.connection_accepted();
+        metrics.set_players_waiting(1);
+        metrics.match_started();
+        metrics.match_started();
+        metrics.shot_fired();
+        metrics.match_completed(Duration::from_millis(1500));
+        metrics.match_aborted();
+        metrics.disconnected(DisconnectReason::from_error(&IoError::from(
+            ErrorKind::BrokenPipe,
+        )));
+        metrics.disconnected(DisconnectReason::from_error(
+            Box::<dyn std::error::Error>::from("wrong password").as_ref(),
+        ));
+        let output = metrics.render();
+        for line in &[
+            "# TYPE battleship_connections_accepted_total counter",
+            "battleship_connections_accepted_total 2",
+            "battleship_active_matches 0",
+            "battleship_players_waiting 1",
+            "battleship_games_completed_total 1",
+            "battleship_shots_fired_total 1",
+            "battleship_game_duration_seconds_average 1.500",
+            "battleship_disconnects_total{reason=\"broken_pipe\"} 1",
+            "battleship_disconnects_total{reason=\"timeout\"} 0",
+            "battleship_disconnects_total{reason=\"game_error\"} 1",
+        ] {
+            assert!(output.lines().any(|l| l == *line), "{} is missing", line);
+        }
+    }
+
+    #[test]
+    fn test_serve() -> Result<()> {
+        let address = serve("127.0.0.1:0")?;
+        let request = |request: &[u8]| -> Result<String> {
+            let mut stream = TcpStream::connect(address)?;
+            stream.write_all(request)?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        };
+        let response = request(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\n\r\n# HELP battleship_"));
+        assert!(response.contains("\nbattleship_active_matches "));
+        let response = request(b"GET / HTTP/1.1\r\n\r\n")?;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        // Oversized and unfinished requests are dropped.
+        let mut stream = TcpStream::connect(address)?;
+        let _ = stream.write_all(&[b'x'; MAX_REQUEST_SIZE + 1]);
+        let mut response = String::new();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.is_empty());
+        let mut stream = TcpStream::connect(address)?;
+        stream.write_all(b"GET /metrics HTTP/1.1\r\n")?;
+        let start = Instant::now();
+        let _ = stream.read_to_string(&mut response);
+        assert!(response.is_empty());
+        assert!(start.elapsed() < REQUEST_TIMEOUT * 2);
+        Ok(())
+    }
+}
@@ -2,55 +2,382 @@
 
 use crate::grid::Coordinate;
 use crate::grid::Grid;
-use crate::{Result, BANNER};
-use std::io::{BufRead, BufReader, Write};
+use crate::limits::RateLimiter;
+use crate::logger::Context;
+use crate::registry::Registry;
+use crate::weapon::Ammo;
+use crate::{log, Result};
+use std::io::{Error as IoError, ErrorKind, Read, Write};
 use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// ID of the next player.
+static NEXT_PLAYER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Maximum length of a line that is read from a player in bytes.
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+/// Maximum length of a player name in characters.
+pub const MAX_NAME_LENGTH: usize = 20;
+
+/// Time limit of sending a message to a player.
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Writes the given message to the stream within the given time limit.
+///
+/// Time limit is absolute, i.e. it is not extended by a peer that reads slowly.
+/// Non-blocking streams fail with [`ErrorKind::WouldBlock`] instead of waiting.
+pub fn send_timeout(mut stream: &TcpStream, message: &str, timeout: Duration) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+    let mut bytes = message.as_bytes();
+    while !bytes.is_empty() {
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(IoError::new(ErrorKind::TimedOut, "write timed out").into());
+        }
+        stream.set_write_timeout(Some(deadline - now))?;
+        match stream.write(bytes) {
+            Ok(0) => return Err(IoError::from(ErrorKind::WriteZero).into()),
+            Ok(size) => bytes = &bytes[size..],
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Skips the parameters of a control sequence until its final character (`@` to `~`).
+fn skip_control_sequence<I: Iterator<Item = char>>(chars: &mut I) {
+    for c in chars {
+        if ('@'..='~').contains(&c) {
+            break;
+        }
+    }
+}
+
+/// Returns whether the character is an invisible formatting character.
+///
+/// Zero-width characters, directional marks and overrides, invisible operators and the
+/// byte order mark are used for making names look like the other ones.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{feff}'
+    )
+}
+
+/// Removes the terminal escape sequences, the control characters and the invisible
+/// characters from the name.
+///
+/// Whitespace is collapsed and the name is truncated to [`MAX_NAME_LENGTH`] characters.
+pub fn sanitize_name(name: &str) -> String {
+    let mut sanitized = String::new();
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' if chars.peek() == Some(&'[') => {
+                chars.next();
+                skip_control_sequence(&mut chars);
+            }
+            '\u{9b}' => skip_control_sequence(&mut chars),
+            // Operating system commands and the other strings end with BEL or ST (`ESC \`).
+            '\u{1b}' if chars.peek().map_or(false, |c| "]PX^_".contains(*c)) => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' || c == '\u{9c}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Other escape sequences consist of a single character.
+            '\u{1b}' => {
+                chars.next();
+            }
+            c if c.is_whitespace() => sanitized.push(' '),
+            c if c.is_control() || is_invisible(c) => {}
+            c => sanitized.push(c),
+        }
+    }
+    sanitized
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .chars()
+        .take(MAX_NAME_LENGTH)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
 
 /// Representation of a player.
 #[derive(Debug)]
 pub struct Player {
+    /// Unique ID of the connection.
+    pub id: u64,
     /// Name of the player.
     pub name: String,
     /// Player's grid.
     pub grid: Grid,
     /// Player's hits.
     pub hits: Vec<Coordinate>,
+    /// Player's special weapons.
+    pub ammo: Ammo,
     /// TCP connection.
     stream: TcpStream,
+    /// Received bytes that are not read as a line yet.
+    buffer: Vec<u8>,
+    /// Registry that the connection is added to.
+    registry: Option<Arc<Registry>>,
+    /// Rate limiter of the lines that are read.
+    rate_limiter: Option<RateLimiter>,
+    /// Time limit of the reads.
+    deadline: Option<Instant>,
 }
 
 impl Player {
     /// Constructs a new instance of [`Player`].
     pub fn new(stream: TcpStream) -> Self {
         Self {
+            id: NEXT_PLAYER_ID.fetch_add(1, Ordering::Relaxed),
             name: String::new(),
             grid: Grid::default(),
             hits: Vec::new(),
+            ammo: Ammo::default(),
             stream,
+            buffer: Vec::new(),
+            registry: None,
+            rate_limiter: None,
+            deadline: None,
         }
     }
 
-    /// Greets the player with a message and sets the name.
-    pub fn greet(&mut self) -> Result<()> {
-        self.send(&format!("{}\nPlease enter your name: ", BANNER))?;
-        self.name = self.read()?;
-        if self.name.is_empty() {
-            self.name = String::from("unknown player");
+    /// Limits the number of the lines that are read per second, 0 for no limit.
+    ///
+    /// Reads that exceed the limit are delayed.
+    pub fn set_rate_limit(&mut self, lines_per_second: u32) {
+        self.rate_limiter = if lines_per_second == 0 {
+            None
+        } else {
+            Some(RateLimiter::new(lines_per_second))
+        };
+    }
+
+    /// Sets the time limit of the next reads, e.g. for the handshake.
+    ///
+    /// Reads fail with a timeout error after the time runs out.
+    pub fn set_deadline(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.deadline = timeout.map(|timeout| Instant::now() + timeout);
+        if self.deadline.is_none() {
+            self.stream.set_read_timeout(None)?;
         }
         Ok(())
     }
 
-    /// Writes the given message to the TCP stream.
+    /// Returns whether if the deadline is exceeded at the given time.
+    pub fn is_expired(&self, now: Instant) -> bool {
+        self.deadline.map_or(false, |deadline| now >= deadline)
+    }
+
+    /// Returns how long the next read is delayed by the rate limit at the given time.
+    pub fn throttle(&self, now: Instant) -> Duration {
+        self.rate_limiter
+            .as_ref()
+            .map(|rate_limiter| rate_limiter.delay(now))
+            .unwrap_or_default()
+    }
+
+    /// Returns whether if a whole line is already received.
+    pub fn has_line(&self) -> bool {
+        self.buffer.contains(&b'\n')
+    }
+
+    /// Adds the connection to the given registry.
+    ///
+    /// The connection is removed from the registry when the player is dropped.
+    pub fn register(&mut self, registry: Arc<Registry>) -> Result<()> {
+        registry.add(self.id, &self.stream)?;
+        self.registry = Some(registry);
+        Ok(())
+    }
+
+    /// Sets the name of the player.
+    ///
+    /// Name is sanitized with [`sanitize_name`] and empty names are replaced with "unknown player".
+    /// The name is not reserved in the registry until [`Player::reserve_name`] or
+    /// [`Player::claim_name`] is called.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = match sanitize_name(name) {
+            name if name.is_empty() => String::from("unknown player"),
+            name => name,
+        };
+    }
+
+    /// Reserves the name of the player in the registry.
+    ///
+    /// Names that are taken by the other connections or for which `is_reserved` returns `true`
+    /// get a numeric suffix. See [`Registry::set_name`].
+    pub fn reserve_name<F: Fn(&str) -> bool>(&mut self, is_reserved: F) {
+        if let Some(registry) = &self.registry {
+            self.name = registry.set_name(self.id, &self.name, is_reserved);
+        }
+    }
+
+    /// Reserves the name of the player in the registry as is.
+    ///
+    /// Returns `false` if the name is taken by another connection.
+    pub fn claim_name(&self) -> bool {
+        self.registry
+            .as_ref()
+            .map_or(true, |registry| registry.claim_name(self.id, &self.name))
+    }
+
+    /// Returns the TCP stream, e.g. for waiting until it is readable.
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Moves the TCP stream into or out of non-blocking mode.
+    ///
+    /// See [`Player::try_read`].
+    pub fn set_nonblocking(&self, nonblocking: bool) -> Result<()> {
+        Ok(self.stream.set_nonblocking(nonblocking)?)
+    }
+
+    /// Writes the given message to the TCP stream within [`WRITE_TIMEOUT`].
     pub fn send(&mut self, message: &str) -> Result<()> {
-        Ok(self.stream.write_all(message.as_bytes())?)
+        send_timeout(&self.stream, message, WRITE_TIMEOUT)
+    }
+
+    /// Takes the next line from the received bytes.
+    ///
+    /// Fails if the line is longer than [`MAX_LINE_LENGTH`].
+    fn take_line(&mut self) -> Result<Option<String>> {
+        match self.buffer.iter().position(|byte| *byte == b'\n') {
+            Some(index) if index <= MAX_LINE_LENGTH => {
+                let line = self.buffer.drain(..=index).collect::<Vec<u8>>();
+                Ok(Some(String::from_utf8_lossy(&line).trim().to_string()))
+            }
+            Some(_) => Err("line is too long".into()),
+            None if self.buffer.len() > MAX_LINE_LENGTH => Err("line is too long".into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Receives the available bytes from the TCP stream.
+    ///
+    /// Returns the number of the received bytes, 0 at the end of the stream.
+    fn receive(&mut self) -> std::io::Result<usize> {
+        let mut buffer = [0; 512];
+        let size = self.stream.read(&mut buffer)?;
+        self.buffer.extend_from_slice(&buffer[..size]);
+        Ok(size)
     }
 
     /// Reads the next line from the TCP stream.
+    ///
+    /// Respects the rate limit and the deadline if they are set.
+    /// Returns the rest of the received bytes at the end of the stream and
+    /// an error if nothing is left.
     pub fn read(&mut self) -> Result<String> {
-        let mut reader = BufReader::new(&self.stream);
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
-        Ok(line.trim().to_string())
+        if let Some(rate_limiter) = &mut self.rate_limiter {
+            thread::sleep(rate_limiter.take(Instant::now()));
+        }
+        match self.read_until(self.deadline)? {
+            Some(line) => Ok(line),
+            None => Err(self.deadline_exceeded()),
+        }
+    }
+
+    /// Reads the next line from the TCP stream before the given deadline.
+    ///
+    /// Deadline is checked before every read, so trickling bytes does not extend it.
+    /// Returns `None` if the deadline is exceeded.
+    fn read_until(&mut self, deadline: Option<Instant>) -> Result<Option<String>> {
+        loop {
+            if let Some(line) = self.take_line()? {
+                return Ok(Some(line));
+            }
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                self.stream.set_read_timeout(Some(deadline - now))?;
+            }
+            match self.receive() {
+                Ok(0) if self.buffer.is_empty() => {
+                    return Err(IoError::from(ErrorKind::UnexpectedEof).into())
+                }
+                Ok(0) => {
+                    let line = self.buffer.drain(..).collect::<Vec<u8>>();
+                    return Ok(Some(String::from_utf8_lossy(&line).trim().to_string()));
+                }
+                Ok(_) => {}
+                Err(e)
+                    if deadline.is_some()
+                        && (e.kind() == ErrorKind::WouldBlock
+                            || e.kind() == ErrorKind::TimedOut) =>
+                {
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Reads the next line from the TCP stream if it is already received.
+    ///
+    /// The stream should be in non-blocking mode.
+    /// Returns `None` if a whole line is not received yet or the rate limit is exceeded.
+    pub fn try_read(&mut self) -> Result<Option<String>> {
+        let now = Instant::now();
+        if self.is_expired(now) {
+            return Err(self.deadline_exceeded());
+        }
+        if self.throttle(now) > Duration::from_secs(0) {
+            return Ok(None);
+        }
+        while !self.has_line() {
+            match self.receive() {
+                Ok(0) => return Err(IoError::from(ErrorKind::UnexpectedEof).into()),
+                Ok(_) => {}
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let line = self.take_line()?;
+        if line.is_some() {
+            if let Some(rate_limiter) = &mut self.rate_limiter {
+                rate_limiter.take(now);
+            }
+        }
+        Ok(line)
+    }
+
+    /// Informs the player that the deadline is exceeded and returns the error.
+    fn deadline_exceeded(&mut self) -> Box<dyn std::error::Error> {
+        let _ = self.send("\nTime is up.\n");
+        IoError::new(ErrorKind::TimedOut, "deadline is exceeded").into()
+    }
+
+    /// Reads the next line from the TCP stream within the given time limit.
+    ///
+    /// Time limit is absolute, i.e. it is not extended by the bytes that are received.
+    /// Returns `None` if the time runs out.
+    pub fn read_timeout(&mut self, timeout: Option<Duration>) -> Result<Option<String>> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        if let Some(rate_limiter) = &mut self.rate_limiter {
+            thread::sleep(rate_limiter.take(Instant::now()));
+        }
+        let line = self.read_until(deadline);
+        self.stream.set_read_timeout(None)?;
+        line
     }
 
     /// Shuts down the TCP connection.
@@ -60,13 +387,26 @@ impl Player {
     }
 }
 
-/// Shut down the TCP connection when the object goes out of scope.
+/// Unregister and shut down the TCP connection when the object goes out of scope.
 impl Drop for Player {
     fn drop(&mut self) {
+        if let Some(registry) = &self.registry {
+            registry.remove(self.id);
+        }
         if let Ok(peer_addr) = self.stream.peer_addr() {
-            println!("[+] Ending TCP connection with {:?}", peer_addr);
+            log!(
+                Info,
+                Context::player(self.id),
+                "Ending TCP connection with {}",
+                peer_addr
+            );
             if let Err(e) = self.exit() {
-                eprintln!("[!] Failed to end TCP connection: {}", e)
+                log!(
+                    Error,
+                    Context::player(self.id),
+                    "Failed to end TCP connection: {}",
+                    e
+                )
             }
         }
     }
@@ -76,18 +416,93 @@ impl Drop for Player {
 mod tests {
     use super::*;
     use std::net::TcpListener;
-    use std::thread;
 
     #[test]
-    #[ignore]
     fn test_player() -> Result<()> {
-        let address = "0.0.0.0:3333";
-        let listener = TcpListener::bind(address)?;
-        thread::spawn(move || listener.accept());
-        let stream = TcpStream::connect(address)?;
-        let mut player = Player::new(stream);
-        player.greet()?;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        player.set_name("");
         assert_eq!("unknown player", player.name);
+        player.set_name("\u{1b}[2J\u{1b}[31mmallory\u{1b}[0m\u{1b}]0;pwned\u{7}\r\n bot");
+        assert_eq!("mallory bot", player.name);
+        player.set_name("\u{202e}ecila\u{202c} \u{200b}\u{feff}bob\u{2060}");
+        assert_eq!("ecila bob", player.name);
+        player.set_name(&"x".repeat(10 * 1024));
+        assert_eq!("x".repeat(MAX_NAME_LENGTH), player.name);
+
+        player.set_nonblocking(true)?;
+        assert!(player.try_read()?.is_none());
+        client.write_all(b"alice\nfire")?;
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(Some(String::from("alice")), player.try_read()?);
+        assert!(player.try_read()?.is_none());
+        player.set_nonblocking(false)?;
+        client.write_all(b" C4\r\n")?;
+        assert_eq!("fire C4", player.read()?);
+
+        client.write_all(&[b'x'; MAX_LINE_LENGTH + 1])?;
+        assert!(player.read().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_read() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        client.write_all(b"alice\nbob")?;
+        assert_eq!("alice", player.read()?);
+        client.write_all(b"\n")?;
+        assert_eq!("bob", player.read()?);
+
+        client.write_all(&[b'x'; MAX_LINE_LENGTH + 1])?;
+        assert!(player.read().is_err());
+
+        // Trickling bytes does not extend the deadline.
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        player.set_deadline(Some(Duration::from_millis(300)))?;
+        let trickle = thread::spawn(move || {
+            for _ in 0..10 {
+                if client.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let start = Instant::now();
+        assert!(player.read().is_err());
+        assert!(start.elapsed() < Duration::from_millis(900));
+        let _ = trickle.join();
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_timeout() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let mut player = Player::new(listener.accept()?.0);
+        client.write_all(b"A1\n")?;
+        assert_eq!(
+            Some(String::from("A1")),
+            player.read_timeout(Some(Duration::from_secs(1)))?
+        );
+
+        // Trickling bytes does not extend the time limit.
+        let trickle = thread::spawn(move || {
+            for _ in 0..10 {
+                if client.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+        let start = Instant::now();
+        assert_eq!(None, player.read_timeout(Some(Duration::from_millis(300)))?);
+        assert!(start.elapsed() < Duration::from_millis(900));
+        drop(player);
+        let _ = trickle.join();
         Ok(())
     }
 }
@@ -0,0 +1,101 @@
+//! Worker pool.
+
+use crate::log;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+/// Job that is run by a worker.
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Fixed number of threads that run the submitted jobs in order.
+///
+/// See <https://doc.rust-lang.org/book/ch20-02-multithreaded.html>
+#[derive(Debug)]
+pub struct ThreadPool {
+    /// Sender of the jobs.
+    sender: Option<Sender<Job>>,
+    /// Worker threads.
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ThreadPool {
+    /// Constructs a new instance of [`ThreadPool`] with the given number of workers.
+    ///
+    /// At least one worker is started.
+    pub fn new(size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..size.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || Self::work(&receiver))
+            })
+            .collect();
+        Self {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    /// Runs the jobs until the pool is dropped.
+    ///
+    /// Panics of the jobs are logged and do not stop the worker.
+    fn work(receiver: &Mutex<Receiver<Job>>) {
+        loop {
+            let job = match receiver.lock().expect("failed to retrieve jobs").recv() {
+                Ok(job) => job,
+                Err(_) => break,
+            };
+            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                log!(Error, "Worker job panicked.");
+            }
+        }
+    }
+
+    /// Submits a job to be run by the next idle worker.
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Some(sender) = &self.sender {
+            let _ = sender.send(Box::new(job));
+        }
+    }
+}
+
+/// Wait for the submitted jobs to finish when the pool goes out of scope.
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        self.sender.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_pool() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let pool = ThreadPool::new(4);
+        assert_eq!(4, pool.workers.len());
+        for i in 0..100 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                if i == 50 {
+                    panic!("job failed");
+                }
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        drop(pool);
+        assert_eq!(99, counter.load(Ordering::Relaxed));
+        assert_eq!(1, ThreadPool::new(0).workers.len());
+    }
+}
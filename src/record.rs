@@ -0,0 +1,444 @@
+//! Game records.
+
+use crate::game::{Command, MAX_PLAYERS};
+use crate::grid::{CellState, Coordinate};
+use crate::map::Map;
+use crate::rules::{Fleet, Mode, Rules};
+use crate::ship::{Ship, ShipType};
+use crate::Result;
+use std::error::Error as StdError;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Version of the record format.
+pub const RECORD_VERSION: u8 = 1;
+/// File extension of the record files.
+pub const RECORD_EXTENSION: &str = "record";
+/// The prefix of the comment lines in a record file.
+const COMMENT_PREFIX: char = ';';
+/// The separator between the command and its results.
+const RESULT_SEPARATOR: &str = "->";
+/// The separator between the ship name and its parts.
+const SHIP_SEPARATOR: char = ':';
+
+/// Returns the current time as seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Returns the name of the cell state that is used in the records.
+fn state_name(state: CellState) -> &'static str {
+    match state {
+        CellState::Unknown => "unknown",
+        CellState::Miss => "miss",
+        CellState::Hit => "hit",
+        CellState::Sunk => "sunk",
+        CellState::Detected => "detected",
+        CellState::Clear => "clear",
+    }
+}
+
+/// Parses the cell state from its name in the records.
+fn parse_state(name: &str) -> Option<CellState> {
+    vec![
+        CellState::Unknown,
+        CellState::Miss,
+        CellState::Hit,
+        CellState::Sunk,
+        CellState::Detected,
+        CellState::Clear,
+    ]
+    .into_iter()
+    .find(|state| state_name(*state) == name)
+}
+
+/// Initial state of a player in the record.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerRecord {
+    /// Name of the player.
+    pub name: String,
+    /// Ships of the player at the start of the game.
+    pub ships: Vec<Ship>,
+}
+
+/// A turn that is played in the game.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Turn {
+    /// Time of the turn as seconds since the Unix epoch.
+    pub time: u64,
+    /// Index of the player who played the turn.
+    pub player: usize,
+    /// Command of the player.
+    pub command: Command,
+    /// Resulting states of the affected cells.
+    pub results: Vec<(Coordinate, CellState)>,
+}
+
+/// Record of a finished game.
+///
+/// Records are saved as text files where each line is a `key = value` pair
+/// and lines starting with `;` are ignored. Players are numbered from 1.
+///
+/// ```text
+/// ; Battleship game record
+/// version = 1
+/// fleet = classic
+/// mode = standard
+/// started = 1697040000
+/// finished = 1697040300
+/// winner = 1
+/// map = ..........
+/// map = ...##.....
+/// player = orhun
+/// ship = Destroyer: ▯ A1 A2
+/// player = alice
+/// ship = Carrier: ▩ C3 D3 E3 F3 G3
+/// turn = 1697040010 1 fire C4 -> C4 miss
+/// turn = 1697040012 2 airstrike C2 -> A2 hit, B2 miss, C2 miss, D2 miss, E2 miss
+/// turn = 1697040015 1 move A1 south ->
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Record {
+    /// Rules of the game.
+    ///
+    /// Custom ship definitions are not recorded.
+    pub rules: Rules,
+    /// Map that the grids are built on.
+    pub map: Map,
+    /// Players of the game.
+    pub players: Vec<PlayerRecord>,
+    /// Turns of the game in the order they are played.
+    pub turns: Vec<Turn>,
+    /// Start time of the game as seconds since the Unix epoch.
+    pub started: u64,
+    /// End time of the game as seconds since the Unix epoch.
+    pub finished: u64,
+    /// Index of the player who won the game.
+    pub winner: Option<usize>,
+}
+
+/// Display the record in the record file format.
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} Battleship game record", COMMENT_PREFIX)?;
+        writeln!(f, "version = {}", RECORD_VERSION)?;
+        writeln!(f, "fleet = {}", self.rules.fleet)?;
+        writeln!(f, "mode = {}", self.rules.mode)?;
+        writeln!(f, "started = {}", self.started)?;
+        writeln!(f, "finished = {}", self.finished)?;
+        if let Some(winner) = self.winner {
+            writeln!(f, "winner = {}", winner + 1)?;
+        }
+        for row in self.map.to_string().lines() {
+            writeln!(f, "map = {}", row)?;
+        }
+        for player in &self.players {
+            writeln!(f, "player = {}", player.name)?;
+            for ship in &player.ships {
+                write!(f, "ship = {}{} {}", ship.name, SHIP_SEPARATOR, ship.glyph)?;
+                for coord in &ship.coords {
+                    write!(f, " {}", coord)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        for turn in &self.turns {
+            write!(
+                f,
+                "turn = {} {} {} {}",
+                turn.time,
+                turn.player + 1,
+                turn.command,
+                RESULT_SEPARATOR
+            )?;
+            for (i, (coord, state)) in turn.results.iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(f, "{}{} {}", separator, coord, state_name(*state))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the record from the contents of a record file.
+impl FromStr for Record {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        let mut record = Self::default();
+        let mut map_rows = Vec::new();
+        let mut winner = None;
+        for (i, line) in value.lines().enumerate() {
+            let line = line.trim_end();
+            if line.trim().is_empty() || line.trim_start().starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            let mut parts = line.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value),
+                _ => return Err(error(format!("invalid line: {}", line)).into()),
+            };
+            // Leading spaces are significant in the map rows.
+            if key == "map" {
+                map_rows.push(value.strip_prefix(' ').unwrap_or(value));
+                continue;
+            }
+            let value = value.trim();
+            let number = |value: &str| {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| error(format!("invalid {}: {}", key, value)))
+            };
+            let player = |value: &str, players: usize| match value.parse::<usize>() {
+                Ok(player) if (1..=players).contains(&player) => Ok(player - 1),
+                _ => Err(error(format!("invalid player: {}", value))),
+            };
+            match key {
+                "version" => {
+                    if number(value)? != u64::from(RECORD_VERSION) {
+                        return Err(error(format!("unsupported version: {}", value)).into());
+                    }
+                }
+                "fleet" => {
+                    record.rules.fleet = match value {
+                        "custom" => Fleet::Custom(Vec::new()),
+                        _ => value.parse().map_err(|e| error(format!("{}", e)))?,
+                    }
+                }
+                "mode" => {
+                    record.rules.mode =
+                        value.parse::<Mode>().map_err(|e| error(format!("{}", e)))?
+                }
+                "started" => record.started = number(value)?,
+                "finished" => record.finished = number(value)?,
+                "winner" => winner = Some(number(value)?),
+                "player" => record.players.push(PlayerRecord {
+                    name: value.to_string(),
+                    ships: Vec::new(),
+                }),
+                "ship" => {
+                    let player = match record.players.last_mut() {
+                        Some(player) => player,
+                        None => return Err(error(String::from("expected a player first")).into()),
+                    };
+                    let mut parts = value.rsplitn(2, SHIP_SEPARATOR);
+                    let (parts, name) = match (parts.next(), parts.next()) {
+                        (Some(parts), Some(name)) => (parts, name.trim()),
+                        _ => return Err(error(format!("invalid ship: {}", value)).into()),
+                    };
+                    let mut parts = parts.split_whitespace();
+                    let glyph = parts.next().unwrap_or_default().to_string();
+                    let coords = parts
+                        .map(Coordinate::from_str)
+                        .collect::<StdResult<Vec<Coordinate>, _>>()
+                        .map_err(|e| error(format!("invalid ship: {}", e)))?;
+                    if coords.is_empty() {
+                        return Err(error(format!("invalid ship: {}", value)).into());
+                    }
+                    player.ships.push(Ship {
+                        type_: ShipType::Custom,
+                        coords,
+                        name: name.to_string(),
+                        glyph,
+                    });
+                }
+                "turn" => {
+                    let mut parts = value.splitn(2, RESULT_SEPARATOR);
+                    let (turn, results) = match (parts.next(), parts.next()) {
+                        (Some(turn), Some(results)) => (turn, results.trim()),
+                        _ => return Err(error(format!("invalid turn: {}", value)).into()),
+                    };
+                    let mut parts = turn.trim().splitn(3, ' ');
+                    let (time, player_number, command) =
+                        match (parts.next(), parts.next(), parts.next()) {
+                            (Some(time), Some(player), Some(command)) => (time, player, command),
+                            _ => return Err(error(format!("invalid turn: {}", value)).into()),
+                        };
+                    let results = results
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|result| !result.is_empty())
+                        .map(|result| {
+                            let mut parts = result.split_whitespace();
+                            match (
+                                parts.next().map(Coordinate::from_str),
+                                parts.next().and_then(parse_state),
+                            ) {
+                                (Some(Ok(coord)), Some(state)) => Some((coord, state)),
+                                _ => None,
+                            }
+                        })
+                        .collect::<Option<Vec<(Coordinate, CellState)>>>()
+                        .ok_or_else(|| error(format!("invalid turn results: {}", results)))?;
+                    record.turns.push(Turn {
+                        time: number(time)?,
+                        player: player(player_number, record.players.len())?,
+                        command: command
+                            .parse()
+                            .map_err(|e| error(format!("invalid command: {}", e)))?,
+                        results,
+                    });
+                }
+                _ => return Err(error(format!("invalid option: {}", line)).into()),
+            }
+        }
+        if record.players.len() != MAX_PLAYERS {
+            return Err(format!(
+                "expected {} players, found {}",
+                MAX_PLAYERS,
+                record.players.len()
+            )
+            .into());
+        }
+        record.map = map_rows.join("\n").parse()?;
+        record.winner = match winner {
+            Some(winner) if (1..=record.players.len() as u64).contains(&winner) => {
+                Some(winner as usize - 1)
+            }
+            Some(winner) => return Err(format!("invalid winner: {}", winner).into()),
+            None => None,
+        };
+        Ok(record)
+    }
+}
+
+impl Record {
+    /// Constructs a new instance of [`Record`] for a game that starts now.
+    pub fn new(rules: Rules, map: Map) -> Self {
+        Self {
+            rules,
+            map,
+            started: now(),
+            ..Self::default()
+        }
+    }
+
+    /// Loads the record from the given file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        fs::read_to_string(&path)?.parse().map_err(|e| {
+            format!(
+                "Failed to load record from {}: {}",
+                path.as_ref().display(),
+                e
+            )
+            .into()
+        })
+    }
+
+    /// Saves the record to the given directory.
+    ///
+    /// File is named after the start time and the names of the players.
+    /// Returns the path of the saved file.
+    pub fn save<P: AsRef<Path>>(&self, directory: P) -> Result<PathBuf> {
+        fs::create_dir_all(&directory)?;
+        let name = self
+            .players
+            .iter()
+            .map(|player| {
+                player
+                    .name
+                    .chars()
+                    .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                    .collect::<String>()
+            })
+            .collect::<Vec<String>>()
+            .join("-vs-");
+        let mut path = directory
+            .as_ref()
+            .join(format!("{}-{}.{}", self.started, name, RECORD_EXTENSION));
+        let mut i = 1;
+        while path.exists() {
+            i += 1;
+            path = directory.as_ref().join(format!(
+                "{}-{}-{}.{}",
+                self.started, name, i, RECORD_EXTENSION
+            ));
+        }
+        fs::write(&path, self.to_string())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::Orientation;
+    use std::env;
+
+    #[test]
+    fn test_record() -> Result<()> {
+        let mut record = Record::new(
+            Rules {
+                fleet: Fleet::Classic,
+                mode: Mode::Advanced,
+            },
+            Map::from_str("....\n .#.\n  ..")?,
+        );
+        record.players.push(PlayerRecord {
+            name: String::from("orhun"),
+            ships: vec![Ship::new(
+                ShipType::Destroyer(Orientation::North),
+                vec![Coordinate::from((1, 1)), Coordinate::from((1, 2))],
+            )],
+        });
+        record.players.push(PlayerRecord {
+            name: String::from("alice = bob"),
+            ships: vec![Ship::new(ShipType::Boat, vec![Coordinate::from((4, 3))])],
+        });
+        record.turns.push(Turn {
+            time: record.started + 1,
+            player: 0,
+            command: Command::Airstrike(Coordinate::from((3, 3))),
+            results: vec![
+                (Coordinate::from((3, 3)), CellState::Miss),
+                (Coordinate::from((4, 3)), CellState::Sunk),
+            ],
+        });
+        record.turns.push(Turn {
+            time: record.started + 2,
+            player: 1,
+            command: Command::Move(Coordinate::from((1, 1)), Orientation::East),
+            results: Vec::new(),
+        });
+        record.finished = record.started + 2;
+        record.winner = Some(0);
+
+        let record_str = record.to_string();
+        assert!(record_str.contains("\nmap =  .#.\n"));
+        assert!(record_str.contains("\nship = Boat: △ D3\n"));
+        assert!(record_str.contains(" 1 airstrike C3 -> C3 miss, D3 sunk\n"));
+        assert!(record_str.contains(" 2 move A1 east ->\n"));
+        let parsed = Record::from_str(&record_str)?;
+        assert_eq!(record_str, parsed.to_string());
+        assert_eq!(record.map, parsed.map);
+        assert_eq!(record.turns, parsed.turns);
+        assert_eq!(Some(0), parsed.winner);
+        assert_eq!("alice = bob", parsed.players[1].name);
+
+        assert!(Record::from_str("version = 2\nmap = .").is_err());
+        assert!(Record::from_str("map = .\nwinner = 1").is_err());
+        assert!(Record::from_str("map = .\nship = Boat: △ A1").is_err());
+        assert!(Record::from_str("map = .\nplayer = a\nturn = 1 2 fire A1 ->").is_err());
+        assert!(Record::from_str("map = .\nplayer = a\nturn = 1 1 fire A1 ->").is_err());
+        assert!(Record::from_str("map = .\nplayer = a\nplayer = b\nplayer = c").is_err());
+        assert!(
+            Record::from_str("map = .\nplayer = a\nplayer = b\nturn = 1 3 fire A1 ->").is_err()
+        );
+        assert!(Record::from_str("map = .\nplayer = a\nplayer = b\nturn = 1 2 fire A1 ->").is_ok());
+
+        let directory = env::temp_dir().join(format!("battleship-records-{}", fastrand::u64(..)));
+        let path = record.save(&directory)?;
+        assert_eq!(record_str, Record::load(&path)?.to_string());
+        assert_ne!(path, record.save(&directory)?);
+        fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+}
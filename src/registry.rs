@@ -0,0 +1,437 @@
+//! Registry of the connections and matches.
+//!
+//! Players and games are handled in their own threads, so the registry keeps
+//! what is needed for inspecting and controlling them from outside (e.g. the admin console).
+
+use crate::player::{self, MAX_NAME_LENGTH};
+use crate::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Message that is sent to the players on shutdown.
+pub const SHUTDOWN_MESSAGE: &str = "\nServer shutting down.\n";
+/// Time limit of sending a message to a connection from outside its thread.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Sends the given message to the stream within [`WRITE_TIMEOUT`].
+///
+/// Returns `false` if the message cannot be sent in time.
+fn send(stream: &TcpStream, message: &str) -> bool {
+    player::send_timeout(stream, message, WRITE_TIMEOUT).is_ok()
+}
+
+/// Returns whether the name is taken by a connection other than the given one (ignoring case).
+fn is_taken(connections: &BTreeMap<u64, Connection>, id: u64, name: &str) -> bool {
+    let name = name.to_lowercase();
+    connections
+        .iter()
+        .any(|(other_id, connection)| *other_id != id && connection.name.to_lowercase() == name)
+}
+
+/// Status of a connection.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    /// Entering the name, logging in or waiting in the lobby.
+    Lobby,
+    /// Waiting for an opponent.
+    Waiting,
+    /// Playing in the match with the given ID.
+    Playing(u64),
+    /// Watching the running match.
+    Spectating,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lobby => write!(f, "lobby"),
+            Self::Waiting => write!(f, "waiting"),
+            Self::Playing(match_id) => write!(f, "playing (match {})", match_id),
+            Self::Spectating => write!(f, "spectating"),
+        }
+    }
+}
+
+/// Registered connection.
+#[derive(Debug)]
+struct Connection {
+    /// Name of the player.
+    name: String,
+    /// Address of the peer.
+    peer_addr: SocketAddr,
+    /// Status of the connection.
+    status: Status,
+    /// Time of the connection.
+    connected: Instant,
+    /// Clone of the TCP stream for sending messages and closing the connection.
+    stream: TcpStream,
+}
+
+/// Summary of a connected player.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerInfo {
+    /// ID of the player.
+    pub id: u64,
+    /// Name of the player.
+    pub name: String,
+    /// Address of the peer.
+    pub peer_addr: SocketAddr,
+    /// Status of the connection.
+    pub status: Status,
+    /// Time since the connection.
+    pub connected: Duration,
+}
+
+/// Summary of a running match.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchInfo {
+    /// ID of the match.
+    pub id: u64,
+    /// IDs of the players.
+    pub players: Vec<u64>,
+    /// Time since the start of the match.
+    pub duration: Duration,
+}
+
+/// Registry of the connections and the running matches.
+#[derive(Debug, Default)]
+pub struct Registry {
+    /// Connections by the player IDs.
+    connections: Mutex<BTreeMap<u64, Connection>>,
+    /// Player IDs and the start times of the running matches.
+    matches: Mutex<BTreeMap<u64, (Vec<u64>, Instant)>>,
+    /// Whether if the server is draining.
+    draining: AtomicBool,
+}
+
+impl Registry {
+    /// Registers the connection of a player.
+    pub fn add(&self, id: u64, stream: &TcpStream) -> Result<()> {
+        let connection = Connection {
+            name: String::new(),
+            peer_addr: stream.peer_addr()?,
+            status: Status::Lobby,
+            connected: Instant::now(),
+            stream: stream.try_clone()?,
+        };
+        self.connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .insert(id, connection);
+        Ok(())
+    }
+
+    /// Removes the connection of a player.
+    pub fn remove(&self, id: u64) {
+        self.connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .remove(&id);
+    }
+
+    /// Sets the name of a player.
+    ///
+    /// Names that are taken by the other connections (ignoring case) or for which `is_reserved`
+    /// returns `true` get a numeric suffix (e.g. `alice-2`) which fits in [`MAX_NAME_LENGTH`].
+    /// `is_reserved` is called without holding the lock of the connections.
+    /// Returns the name that is set.
+    pub fn set_name<F: Fn(&str) -> bool>(&self, id: u64, name: &str, is_reserved: F) -> String {
+        let mut i = 1;
+        loop {
+            let unique_name = if i == 1 {
+                name.to_string()
+            } else {
+                let suffix = format!("-{}", i);
+                name.chars()
+                    .take(MAX_NAME_LENGTH.saturating_sub(suffix.len()))
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+                    + &suffix
+            };
+            if !is_reserved(&unique_name) && self.claim_name(id, &unique_name) {
+                return unique_name;
+            }
+            i += 1;
+        }
+    }
+
+    /// Sets the name of a player without a suffix.
+    ///
+    /// Returns `false` if the name is taken by another connection (ignoring case).
+    pub fn claim_name(&self, id: u64, name: &str) -> bool {
+        let mut connections = self
+            .connections
+            .lock()
+            .expect("failed to retrieve connections");
+        if is_taken(&connections, id, name) {
+            return false;
+        }
+        if let Some(connection) = connections.get_mut(&id) {
+            connection.name = name.to_string();
+        }
+        true
+    }
+
+    /// Sets the status of a player.
+    pub fn set_status(&self, id: u64, status: Status) {
+        if let Some(connection) = self
+            .connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .get_mut(&id)
+        {
+            connection.status = status;
+        }
+    }
+
+    /// Registers a match that is started with the given players.
+    pub fn start_match(&self, match_id: u64, players: &[u64]) {
+        for id in players {
+            self.set_status(*id, Status::Playing(match_id));
+        }
+        self.matches
+            .lock()
+            .expect("failed to retrieve matches")
+            .insert(match_id, (players.to_vec(), Instant::now()));
+    }
+
+    /// Removes a match that is ended.
+    pub fn end_match(&self, match_id: u64) {
+        self.matches
+            .lock()
+            .expect("failed to retrieve matches")
+            .remove(&match_id);
+    }
+
+    /// Returns the connected players.
+    pub fn players(&self) -> Vec<PlayerInfo> {
+        self.connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .iter()
+            .map(|(id, connection)| PlayerInfo {
+                id: *id,
+                name: connection.name.to_string(),
+                peer_addr: connection.peer_addr,
+                status: connection.status,
+                connected: connection.connected.elapsed(),
+            })
+            .collect()
+    }
+
+    /// Returns the number of the connections.
+    ///
+    /// Only the connections from the given address are counted if it is given.
+    pub fn count(&self, ip: Option<IpAddr>) -> usize {
+        self.connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .values()
+            .filter(|connection| ip.map_or(true, |ip| connection.peer_addr.ip() == ip))
+            .count()
+    }
+
+    /// Returns the running matches.
+    pub fn matches(&self) -> Vec<MatchInfo> {
+        self.matches
+            .lock()
+            .expect("failed to retrieve matches")
+            .iter()
+            .map(|(id, (players, started))| MatchInfo {
+                id: *id,
+                players: players.to_vec(),
+                duration: started.elapsed(),
+            })
+            .collect()
+    }
+
+    /// Returns clones of the TCP streams of the given players, or all players if `None`.
+    ///
+    /// Streams are cloned so that they can be written to without holding the lock.
+    fn streams(&self, ids: Option<&[u64]>) -> Vec<TcpStream> {
+        self.connections
+            .lock()
+            .expect("failed to retrieve connections")
+            .iter()
+            .filter(|(id, _)| ids.map_or(true, |ids| ids.contains(id)))
+            .filter_map(|(_, connection)| connection.stream.try_clone().ok())
+            .collect()
+    }
+
+    /// Sends the given message to a player and closes the connection.
+    ///
+    /// The thread of the player stops at the next read or write.
+    /// Returns `false` if the player is not connected.
+    pub fn disconnect(&self, id: u64, message: &str) -> bool {
+        match self.streams(Some(&[id])).pop() {
+            Some(stream) => {
+                send(&stream, message);
+                let _ = stream.shutdown(Shutdown::Both);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ends a running match by disconnecting its players with the given message.
+    ///
+    /// Returns `false` if the match is not running.
+    pub fn end(&self, match_id: u64, message: &str) -> bool {
+        let players = match self
+            .matches
+            .lock()
+            .expect("failed to retrieve matches")
+            .get(&match_id)
+        {
+            Some((players, _)) => players.to_vec(),
+            None => return false,
+        };
+        for id in players {
+            self.disconnect(id, message);
+        }
+        true
+    }
+
+    /// Ends all the running matches with the given message.
+    ///
+    /// Returns the number of the ended matches.
+    pub fn end_all(&self, message: &str) -> usize {
+        self.matches()
+            .into_iter()
+            .filter(|running| self.end(running.id, message))
+            .count()
+    }
+
+    /// Sends the given message to all connections.
+    ///
+    /// Returns the number of the connections that received the message.
+    pub fn broadcast(&self, message: &str) -> usize {
+        self.streams(None)
+            .iter()
+            .filter(|stream| send(stream, message))
+            .count()
+    }
+
+    /// Starts draining the server.
+    ///
+    /// New connections and games are refused and the players that are not
+    /// in a match are disconnected with the given message.
+    pub fn drain(&self, message: &str) {
+        self.draining.store(true, Ordering::Relaxed);
+        let idle = self
+            .players()
+            .into_iter()
+            .filter(|player| match player.status {
+                Status::Lobby | Status::Waiting => true,
+                Status::Playing(_) | Status::Spectating => false,
+            })
+            .collect::<Vec<PlayerInfo>>();
+        for player in idle {
+            self.disconnect(player.id, message);
+        }
+    }
+
+    /// Returns whether if the server is draining.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether if the server is drained, i.e. there are no running matches left.
+    pub fn is_drained(&self) -> bool {
+        self.is_draining()
+            && self
+                .matches
+                .lock()
+                .expect("failed to retrieve matches")
+                .is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_registry() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut client = TcpStream::connect(listener.local_addr()?)?;
+        let (stream, _) = listener.accept()?;
+        let registry = Registry::default();
+        registry.add(1, &stream)?;
+        assert_eq!("alice", registry.set_name(1, "alice", |_| false));
+        registry.start_match(7, &[1, 2]);
+        let players = registry.players();
+        assert_eq!(1, players.len());
+        assert_eq!(1, registry.count(Some(players[0].peer_addr.ip())));
+        assert_eq!(0, registry.count(Some(IpAddr::from([192, 0, 2, 1]))));
+        assert_eq!("alice", players[0].name);
+        assert_eq!(Status::Playing(7), players[0].status);
+        assert_eq!(vec![1, 2], registry.matches()[0].players);
+
+        assert_eq!(1, registry.broadcast("Hello!\n"));
+        registry.drain("Bye.\n");
+        assert!(registry.is_draining());
+        assert!(!registry.is_drained());
+        assert!(registry.end(7, "Match is ended.\n"));
+        let mut lines = BufReader::new(&mut client).lines();
+        assert_eq!("Hello!", lines.next().transpose()?.unwrap_or_default());
+        assert_eq!(
+            "Match is ended.",
+            lines.next().transpose()?.unwrap_or_default()
+        );
+        assert!(lines.next().is_none());
+
+        registry.end_match(7);
+        assert_eq!(0, registry.end_all(SHUTDOWN_MESSAGE));
+        registry.remove(1);
+        assert!(registry.is_drained());
+        assert!(registry.players().is_empty());
+        assert!(!registry.disconnect(1, ""));
+
+        registry.add(1, &stream)?;
+        registry.add(2, &stream)?;
+        registry.add(3, &stream)?;
+        let unreserved = |_: &str| false;
+        assert_eq!("Alice", registry.set_name(1, "Alice", unreserved));
+        assert_eq!("alice-2", registry.set_name(2, "alice", unreserved));
+        assert_eq!("alice-3", registry.set_name(3, "alice", unreserved));
+        assert_eq!("alice-3", registry.set_name(3, "alice", unreserved));
+        assert!(!registry.claim_name(3, "ALICE"));
+        assert!(registry.claim_name(3, "bob"));
+        assert_eq!("bob-3", registry.set_name(2, "bob", |name| name == "bob-2"));
+        // The connections are not locked while checking the reserved names.
+        assert_eq!(
+            "carol",
+            registry.set_name(2, "carol", |_| registry.players().is_empty())
+        );
+        let long_name = "x".repeat(MAX_NAME_LENGTH);
+        registry.set_name(1, &long_name, unreserved);
+        assert_eq!(
+            format!("{}-2", &long_name[..MAX_NAME_LENGTH - 2]),
+            registry.set_name(2, &long_name, unreserved)
+        );
+        let spaced_name = format!("{} xx", "x".repeat(MAX_NAME_LENGTH - 3));
+        registry.set_name(1, &spaced_name, unreserved);
+        assert_eq!(
+            format!("{}-2", "x".repeat(MAX_NAME_LENGTH - 3)),
+            registry.set_name(2, &spaced_name, unreserved)
+        );
+
+        // Connections that do not read do not hold up the others.
+        let registry = Registry::default();
+        let _client = TcpStream::connect(listener.local_addr()?)?;
+        registry.add(1, &listener.accept()?.0)?;
+        let start = Instant::now();
+        assert_eq!(0, registry.broadcast(&"~".repeat(64 * 1024 * 1024)));
+        assert!(start.elapsed() < WRITE_TIMEOUT * 3);
+        assert!(registry.disconnect(1, "Bye.\n"));
+        Ok(())
+    }
+}
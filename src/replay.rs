@@ -0,0 +1,239 @@
+//! Replay of the recorded games.
+
+use crate::game::{Command, MAX_PLAYERS};
+use crate::grid::Grid;
+use crate::record::Record;
+use crate::Result;
+use std::io::{BufRead, Write};
+
+/// Usage of the replay controls.
+const REPLAY_HELP: &str = "[n]ext, [b]ack, <turn> to jump, [q]uit: ";
+
+/// Replay of a [`Record`] that can be stepped through turn by turn.
+#[derive(Clone, Debug)]
+pub struct Replay {
+    /// Record of the game.
+    record: Record,
+    /// Number of the turns that are played.
+    turn: usize,
+    /// Grids of the players after the played turns.
+    grids: Vec<Grid>,
+}
+
+impl Replay {
+    /// Constructs a new instance of [`Replay`] at the start of the game.
+    pub fn new(record: Record) -> Self {
+        let mut replay = Self {
+            record,
+            turn: 0,
+            grids: Vec::new(),
+        };
+        replay.jump(0);
+        replay
+    }
+
+    /// Returns the record of the game.
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+
+    /// Returns the number of the turns that are played.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Returns the grids of the players after the played turns.
+    pub fn grids(&self) -> &[Grid] {
+        &self.grids
+    }
+
+    /// Returns whether if all the turns are played.
+    pub fn is_finished(&self) -> bool {
+        self.turn == self.record.turns.len()
+    }
+
+    /// Plays the next turn.
+    ///
+    /// Returns `false` if all the turns are already played.
+    pub fn forward(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        let turn = &self.record.turns[self.turn];
+        let grid = match turn.command {
+            Command::Move(_, _) => &mut self.grids[turn.player],
+            _ => &mut self.grids[MAX_PLAYERS - (turn.player + 1)],
+        };
+        match turn.command {
+            Command::Fire(coordinate) => {
+                grid.shoot(coordinate);
+            }
+            Command::Sonar(coordinate) => {
+                grid.sonar(coordinate);
+            }
+            Command::Airstrike(coordinate) => {
+                grid.airstrike(coordinate);
+            }
+            Command::Torpedo(coordinate, direction) => {
+                grid.torpedo(coordinate, direction);
+            }
+            Command::Move(coordinate, direction) => {
+                grid.move_ship(coordinate, direction);
+            }
+        }
+        self.turn += 1;
+        true
+    }
+
+    /// Goes back to the previous turn.
+    ///
+    /// Returns `false` if no turns are played yet.
+    pub fn back(&mut self) -> bool {
+        if self.turn == 0 {
+            return false;
+        }
+        self.jump(self.turn - 1);
+        true
+    }
+
+    /// Jumps to the state after the given number of turns.
+    ///
+    /// Turns are played from the start of the game since the shots cannot be undone.
+    pub fn jump(&mut self, turn: usize) {
+        self.turn = 0;
+        self.grids = self
+            .record
+            .players
+            .iter()
+            .map(|player| {
+                let mut grid = Grid::from_map(self.record.map.clone());
+                player.ships.iter().for_each(|ship| {
+                    grid.place_ship(ship.clone());
+                });
+                grid
+            })
+            .collect();
+        while self.turn < turn && self.forward() {}
+    }
+
+    /// Jumps to the end of the game.
+    pub fn finish(&mut self) {
+        while self.forward() {}
+    }
+
+    /// Returns the current state of the game as string.
+    ///
+    /// The last played turn is shown along with the grids of the players.
+    pub fn as_string(&self) -> Result<String> {
+        let mut s = format!("Turn {}/{}", self.turn, self.record.turns.len());
+        if let Some(turn) = self.turn.checked_sub(1).map(|i| &self.record.turns[i]) {
+            s += &format!(
+                ": {} {}",
+                self.record.players[turn.player].name, turn.command
+            );
+        }
+        s.push('\n');
+        for (player, grid) in self.record.players.iter().zip(&self.grids) {
+            s += &format!("{}'s grid:{}", player.name, grid.as_string(true)?);
+        }
+        if self.is_finished() {
+            if let Some(winner) = self.record.winner {
+                s += &format!("{} won.\n", self.record.players[winner].name);
+            }
+        }
+        Ok(s)
+    }
+
+    /// Runs the replay with the controls that are read from the given input.
+    ///
+    /// Each line of the input is either `n` (or empty) for the next turn, `b` for
+    /// the previous turn, a turn number to jump or `q` to quit.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> Result<()> {
+        write!(output, "{}{}", self.as_string()?, REPLAY_HELP)?;
+        output.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            match line.trim().to_lowercase().as_str() {
+                "" | "n" | "next" => {
+                    self.forward();
+                }
+                "b" | "back" => {
+                    self.back();
+                }
+                "q" | "quit" => break,
+                value => match value.parse() {
+                    Ok(turn) if turn <= self.record.turns.len() => self.jump(turn),
+                    _ => writeln!(output, "Invalid control: {}", value)?,
+                },
+            }
+            write!(output, "{}{}", self.as_string()?, REPLAY_HELP)?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{CellState, Coordinate};
+    use crate::map::Map;
+    use crate::record::{PlayerRecord, Turn};
+    use crate::ship::{Ship, ShipType};
+    use std::str::FromStr;
+
+    #[test]
+    fn test_replay() -> Result<()> {
+        let mut record = Record::new(Default::default(), Map::new(3, 3));
+        for name in &["orhun", "alice"] {
+            record.players.push(PlayerRecord {
+                name: name.to_string(),
+                ships: vec![Ship::new(ShipType::Boat, vec![Coordinate::from((1, 1))])],
+            });
+        }
+        for (player, command) in &[(0, "fire B2"), (1, "move A1 east"), (0, "fire B1")] {
+            record.turns.push(Turn {
+                time: 0,
+                player: *player,
+                command: Command::from_str(command)?,
+                results: Vec::new(),
+            });
+        }
+        record.winner = Some(0);
+
+        let mut replay = Replay::new(record);
+        assert!(!replay.back());
+        assert!(replay.forward());
+        assert_eq!(
+            CellState::Miss,
+            replay.grids()[1].cell_state(Coordinate::from((2, 2)))
+        );
+        assert!(replay.as_string()?.starts_with("Turn 1/3: orhun fire B2\n"));
+        replay.finish();
+        assert!(replay.is_finished());
+        assert!(!replay.forward());
+        assert_eq!(
+            CellState::Sunk,
+            replay.grids()[1].cell_state(Coordinate::from((2, 1)))
+        );
+        assert!(replay.as_string()?.ends_with("orhun won.\n"));
+        assert!(replay.back());
+        assert_eq!(2, replay.turn());
+        assert_eq!(
+            CellState::Unknown,
+            replay.grids()[1].cell_state(Coordinate::from((2, 1)))
+        );
+        assert!(replay.grids()[1]
+            .ship_at(Coordinate::from((2, 1)))
+            .is_some());
+
+        let mut output = Vec::new();
+        replay.run("1\nb\nn\nn\n9\nq\nn\n".as_bytes(), &mut output)?;
+        let output = String::from_utf8(output)?;
+        assert!(output.contains("Turn 1/3: orhun fire B2\n"));
+        assert!(output.contains("Turn 0/3\n"));
+        assert!(output.contains("Invalid control: 9\n"));
+        assert_eq!(2, replay.turn());
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Game rules.
+
+use crate::shape::ShipDefinition;
+use crate::ship::{Orientation, ShipType};
+use std::error::Error as StdError;
+use std::fmt;
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+/// Available fleets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fleet {
+    /// Classic Hasbro fleet.
+    ///
+    /// Carrier (5), Battleship (4), Cruiser (3), Submarine (3) and Destroyer (2).
+    Classic,
+    /// Random number of boats, destroyers and one battleship block.
+    Orhun,
+    /// Ships with custom shapes.
+    ///
+    /// See [`ShipDefinition::load_all`].
+    Custom(Vec<ShipDefinition>),
+}
+
+/// Default fleet is the original one.
+impl Default for Fleet {
+    fn default() -> Self {
+        Self::Orhun
+    }
+}
+
+impl fmt::Display for Fleet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Classic => "classic",
+                Self::Orhun => "orhun",
+                Self::Custom(_) => "custom",
+            }
+        )
+    }
+}
+
+impl FromStr for Fleet {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "classic" => Ok(Self::Classic),
+            "orhun" => Ok(Self::Orhun),
+            _ => Err(format!("Invalid fleet: {} (expected classic or orhun)", value).into()),
+        }
+    }
+}
+
+impl Fleet {
+    /// Returns the ship types of the classic fleet with random orientations.
+    ///
+    /// Ships are ordered from the largest to the smallest for easier placement.
+    pub fn classic_ship_types() -> Vec<ShipType> {
+        vec![
+            ShipType::Carrier(Orientation::new_random()),
+            ShipType::ClassicBattleship(Orientation::new_random()),
+            ShipType::Cruiser(Orientation::new_random()),
+            ShipType::Submarine(Orientation::new_random()),
+            ShipType::Destroyer(Orientation::new_random()),
+        ]
+    }
+}
+
+/// Available game modes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Players can only fire single shots.
+    Standard,
+    /// Players have limited-use special weapons in addition to single shots.
+    ///
+    /// See [`Weapon`].
+    ///
+    /// [`Weapon`]: crate::weapon::Weapon
+    Advanced,
+    /// Players can move their undamaged ships one cell instead of firing.
+    Mobile,
+}
+
+/// Default mode is the standard one.
+impl Default for Mode {
+    fn default() -> Self {
+        Self::Standard
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Standard => "standard",
+                Self::Advanced => "advanced",
+                Self::Mobile => "mobile",
+            }
+        )
+    }
+}
+
+impl FromStr for Mode {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "standard" => Ok(Self::Standard),
+            "advanced" => Ok(Self::Advanced),
+            "mobile" => Ok(Self::Mobile),
+            _ => Err(format!(
+                "Invalid mode: {} (expected standard, advanced or mobile)",
+                value
+            )
+            .into()),
+        }
+    }
+}
+
+/// Representation of the game rules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rules {
+    /// Fleet of the players.
+    pub fleet: Fleet,
+    /// Game mode.
+    pub mode: Mode,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Result;
+
+    #[test]
+    fn test_fleet() -> Result<()> {
+        assert_eq!(Fleet::Orhun, Fleet::default());
+        assert_eq!(Fleet::Classic, "Classic".parse()?);
+        assert_eq!(Fleet::Orhun, "orhun".parse()?);
+        assert!("hasbro".parse::<Fleet>().is_err());
+        for fleet in &[Fleet::Classic, Fleet::Orhun] {
+            assert_eq!(*fleet, fleet.to_string().parse()?);
+        }
+        assert_eq!(Mode::Standard, Mode::default());
+        assert_eq!(Mode::Advanced, "advanced".parse()?);
+        assert_eq!(Mode::Mobile, " Mobile".parse()?);
+        assert!("expert".parse::<Mode>().is_err());
+        assert_eq!(
+            vec!["Carrier", "Battleship", "Cruiser", "Submarine", "Destroyer"],
+            Fleet::classic_ship_types()
+                .iter()
+                .map(|ship_type| ship_type.name())
+                .collect::<Vec<&str>>()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,484 @@
+//! Ship shapes.
+
+use crate::grid::Coordinate;
+use crate::ship::Orientation;
+use crate::Result;
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+/// The character that represents a part of the ship in a shape mask.
+const FILLED_CHAR: char = '#';
+/// The character that represents an empty cell in a shape mask.
+const EMPTY_CHAR: char = '.';
+/// The prefix of the comment lines in a ship definitions file.
+const COMMENT_PREFIX: char = ';';
+/// Maximum number of the columns and the rows of a shape mask.
+///
+/// Cells are kept below [`u8::MAX`] so that the width and the height fit in a [`u8`].
+const MAX_MASK_SIZE: u8 = u8::MAX;
+/// Ranges of the characters that do not take exactly one column in a terminal,
+/// e.g. combining marks, zero-width characters and wide (CJK and emoji) characters.
+const NON_SINGLE_WIDTH_RANGES: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x1100, 0x115F),
+    (0x200B, 0x200F),
+    (0x2028, 0x202E),
+    (0x2060, 0x206F),
+    (0x2E80, 0xA4CF),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE00, 0xFE0F),
+    (0xFE30, 0xFE4F),
+    (0xFEFF, 0xFEFF),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F300, 0x1FAFF),
+    (0x20000, 0x3FFFD),
+];
+
+/// Returns whether if the glyph takes exactly one column in a terminal.
+fn is_single_width(glyph: char) -> bool {
+    let code = u32::from(glyph);
+    !glyph.is_control()
+        && !glyph.is_whitespace()
+        && !NON_SINGLE_WIDTH_RANGES
+            .iter()
+            .any(|(start, end)| (*start..=*end).contains(&code))
+}
+
+/// Shape of a ship as a mask of cells relative to its top-left corner.
+///
+/// Cells are kept normalized (aligned to the origin) and in row-major order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Shape {
+    /// Filled cells of the shape as (x, y) offsets.
+    cells: Vec<(u8, u8)>,
+}
+
+impl Shape {
+    /// Constructs a new instance of [`Shape`] from the given cells.
+    pub fn new(cells: Vec<(u8, u8)>) -> Self {
+        let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+        let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+        let mut cells = cells
+            .into_iter()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect::<Vec<(u8, u8)>>();
+        cells.sort_by_key(|(x, y)| (*y, *x));
+        cells.dedup();
+        Self { cells }
+    }
+
+    /// Constructs a new rectangular [`Shape`].
+    pub fn rectangle(width: u8, height: u8) -> Self {
+        Self::new(
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .collect(),
+        )
+    }
+
+    /// Parses the shape from the rows of a mask.
+    ///
+    /// `#` is a part of the ship and `.` is an empty cell.
+    /// Masks can be up to [`MAX_MASK_SIZE`] columns wide and rows high.
+    pub fn from_mask(rows: &[&str]) -> Result<Self> {
+        let mut cells = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            let y = u8::try_from(y)
+                .ok()
+                .filter(|y| *y < MAX_MASK_SIZE)
+                .ok_or("shape has too many rows")?;
+            for (x, c) in row.trim().chars().enumerate() {
+                let x = u8::try_from(x)
+                    .ok()
+                    .filter(|x| *x < MAX_MASK_SIZE)
+                    .ok_or_else(|| {
+                        format!("row {} of the shape is too wide", usize::from(y) + 1)
+                    })?;
+                match c {
+                    FILLED_CHAR => cells.push((x, y)),
+                    EMPTY_CHAR => {}
+                    _ => return Err(format!("unexpected character '{}' in shape", c).into()),
+                }
+            }
+        }
+        if cells.is_empty() {
+            Err("shape is empty".into())
+        } else {
+            Ok(Self::new(cells))
+        }
+    }
+
+    /// Returns the filled cells of the shape.
+    pub fn cells(&self) -> &[(u8, u8)] {
+        &self.cells
+    }
+
+    /// Returns the number of filled cells.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Returns whether if the shape has no cells.
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// Returns the width of the shape.
+    pub fn width(&self) -> u8 {
+        self.cells.iter().map(|c| c.0 + 1).max().unwrap_or(0)
+    }
+
+    /// Returns the height of the shape.
+    pub fn height(&self) -> u8 {
+        self.cells.iter().map(|c| c.1 + 1).max().unwrap_or(0)
+    }
+
+    /// Returns the shape rotated by 90 degrees clockwise.
+    pub fn rotate(&self) -> Self {
+        let height = self.height();
+        Self::new(
+            self.cells
+                .iter()
+                .map(|(x, y)| (height - 1 - y, *x))
+                .collect(),
+        )
+    }
+
+    /// Returns the shape rotated to the given orientation.
+    ///
+    /// See [`Orientation::rotations`].
+    pub fn rotate_to(&self, orientation: Orientation) -> Self {
+        (0..orientation.rotations()).fold(self.clone(), |shape, _| shape.rotate())
+    }
+
+    /// Returns the shape mirrored along the vertical axis.
+    pub fn mirror(&self) -> Self {
+        let width = self.width();
+        Self::new(
+            self.cells
+                .iter()
+                .map(|(x, y)| (width - 1 - x, *y))
+                .collect(),
+        )
+    }
+
+    /// Returns the coordinates of the shape placed at the given coordinate.
+    ///
+    /// Coordinates that overflow are set to zero which is outside of the grid,
+    /// so the ship cannot be placed there.
+    pub fn place(&self, coordinate: Coordinate) -> Vec<Coordinate> {
+        self.cells
+            .iter()
+            .map(|(x, y)| {
+                let mut coord = coordinate;
+                coord.x = coord.x.checked_add(*x).unwrap_or(0);
+                coord.y = coord.y.checked_add(*y).unwrap_or(0);
+                coord
+            })
+            .collect()
+    }
+}
+
+/// Definition of a ship type with a custom shape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShipDefinition {
+    /// Name of the ship.
+    pub name: String,
+    /// The character that represents a part of the ship.
+    pub glyph: char,
+    /// Shape of the ship.
+    pub shape: Shape,
+    /// Whether if the ship can be rotated.
+    pub rotate: bool,
+    /// Whether if the ship can be mirrored.
+    pub mirror: bool,
+    /// Number of ships of this type in the fleet.
+    pub count: usize,
+}
+
+impl ShipDefinition {
+    /// Constructs a new instance of [`ShipDefinition`].
+    pub fn new(name: &str, glyph: char, shape: Shape) -> Self {
+        Self {
+            name: name.to_string(),
+            glyph,
+            shape,
+            rotate: false,
+            mirror: false,
+            count: 1,
+        }
+    }
+
+    /// Returns the distinct shapes that the ship can be placed with.
+    ///
+    /// Rotations and mirror images are included if they are allowed.
+    pub fn variants(&self) -> Vec<Shape> {
+        let mut shapes = vec![self.shape.clone()];
+        if self.mirror {
+            shapes.push(self.shape.mirror());
+        }
+        if self.rotate {
+            shapes = shapes
+                .iter()
+                .flat_map(|shape| {
+                    Orientation::variants()
+                        .into_iter()
+                        .map(move |orientation| shape.rotate_to(orientation))
+                })
+                .collect();
+        }
+        let mut variants: Vec<Shape> = Vec::new();
+        for shape in shapes {
+            if !variants.contains(&shape) {
+                variants.push(shape);
+            }
+        }
+        variants
+    }
+
+    /// Returns a random variant of the ship shape.
+    pub fn random_variant(&self) -> Shape {
+        let variants = self.variants();
+        variants[fastrand::usize(..variants.len())].clone()
+    }
+
+    /// Parses the ship definitions from the contents of a definitions file.
+    ///
+    /// Each definition starts with a `ship = <name>` line which is followed by
+    /// `key = value` options and the rows of the shape mask.
+    ///
+    /// ```text
+    /// ; L-shaped ship that can be rotated and mirrored.
+    /// ship = Ell
+    /// glyph = L
+    /// count = 2
+    /// rotate = true
+    /// mirror = true
+    /// #.
+    /// #.
+    /// ##
+    /// ```
+    pub fn parse_all(value: &str) -> Result<Vec<Self>> {
+        let mut definitions = Vec::new();
+        let mut current: Option<(Self, Vec<&str>)> = None;
+        for (i, line) in value.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(COMMENT_PREFIX) {
+                continue;
+            }
+            let error = |message: String| format!("line {}: {}", i + 1, message);
+            if let Some((key, value)) = split_option(line) {
+                if key == "ship" {
+                    if let Some(definition) = current.take() {
+                        definitions.push(Self::finish(definition)?);
+                    }
+                    current = Some((Self::new(value, '?', Shape::default()), Vec::new()));
+                    continue;
+                }
+                let definition = match current.as_mut() {
+                    Some((definition, _)) => definition,
+                    None => return Err(error(String::from("expected a ship name first")).into()),
+                };
+                match key {
+                    "glyph" => {
+                        let mut chars = value.chars();
+                        definition.glyph = match (chars.next(), chars.next()) {
+                            (Some(glyph), None) if is_single_width(glyph) => glyph,
+                            _ => return Err(error(format!("invalid glyph: {}", value)).into()),
+                        }
+                    }
+                    "count" => {
+                        definition.count = value
+                            .parse()
+                            .map_err(|_| error(format!("invalid count: {}", value)))?
+                    }
+                    "rotate" | "mirror" => {
+                        let value = value
+                            .parse()
+                            .map_err(|_| error(format!("invalid {} value: {}", key, value)))?;
+                        if key == "rotate" {
+                            definition.rotate = value;
+                        } else {
+                            definition.mirror = value;
+                        }
+                    }
+                    _ => return Err(error(format!("invalid option: {}", line)).into()),
+                }
+            } else {
+                match current.as_mut() {
+                    Some((_, rows)) => rows.push(line),
+                    None => return Err(error(String::from("expected a ship name first")).into()),
+                }
+            }
+        }
+        if let Some(definition) = current.take() {
+            definitions.push(Self::finish(definition)?);
+        }
+        if definitions.is_empty() {
+            Err("no ships are defined".into())
+        } else {
+            Ok(definitions)
+        }
+    }
+
+    /// Loads the ship definitions from the given file.
+    pub fn load_all<P: AsRef<Path>>(path: P) -> Result<Vec<Self>> {
+        Self::parse_all(&fs::read_to_string(&path)?).map_err(|e| {
+            format!(
+                "Failed to load ship definitions from {}: {}",
+                path.as_ref().display(),
+                e
+            )
+            .into()
+        })
+    }
+
+    /// Builds the shape of the parsed definition.
+    fn finish((mut definition, rows): (Self, Vec<&str>)) -> Result<Self> {
+        definition.shape = Shape::from_mask(&rows)
+            .map_err(|e| format!("invalid shape for {}: {}", definition.name, e))?;
+        Ok(definition)
+    }
+}
+
+/// Splits the `key = value` option.
+fn split_option(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.splitn(2, '=');
+    match (parts.next(), parts.next()) {
+        (Some(key), Some(value)) => Some((key.trim(), value.trim())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape() -> Result<()> {
+        let shape = Shape::from_mask(&["#.", "#.", "##"])?;
+        assert_eq!(&[(0, 0), (0, 1), (0, 2), (1, 2)], shape.cells());
+        assert_eq!((2, 3, 4), (shape.width(), shape.height(), shape.len()));
+        assert_eq!(Shape::from_mask(&["###", "#.."])?, shape.rotate());
+        assert_eq!(Shape::from_mask(&[".#", ".#", "##"])?, shape.mirror());
+        assert_eq!(shape, shape.rotate().rotate().rotate().rotate());
+        assert_eq!(Shape::from_mask(&["###", "###"])?, Shape::rectangle(3, 2));
+        assert_eq!(
+            vec![
+                Coordinate::from((2, 3)),
+                Coordinate::from((2, 4)),
+                Coordinate::from((2, 5)),
+                Coordinate::from((3, 5))
+            ],
+            shape.place(Coordinate::from((2, 3)))
+        );
+        assert_eq!(
+            vec![Coordinate::from((255, 1)), Coordinate::from((0, 1))],
+            Shape::from_mask(&["#.#"])?.place(Coordinate::from((255, 1)))
+        );
+        let mut rows = vec!["."; 11];
+        rows[0] = "#";
+        rows[10] = "#";
+        assert_eq!(
+            vec![Coordinate::from((250, 250)), Coordinate::from((250, 0))],
+            Shape::from_mask(&rows)?.place(Coordinate::from((250, 250)))
+        );
+        assert!(Shape::from_mask(&["..", ".."]).is_err());
+        assert!(Shape::from_mask(&["#x"]).is_err());
+        Ok(())
+    }
+
+    /// Returns a random non-empty shape that fits in a 5x5 box.
+    fn random_shape() -> Shape {
+        let mut cells = vec![(fastrand::u8(..5), fastrand::u8(..5))];
+        for _ in 0..fastrand::usize(..10) {
+            cells.push((fastrand::u8(..5), fastrand::u8(..5)));
+        }
+        Shape::new(cells)
+    }
+
+    #[test]
+    fn test_shape_rotation_properties() {
+        for _ in 0..100 {
+            let shape = random_shape();
+            for orientation in Orientation::variants() {
+                let rotated = shape.rotate_to(orientation);
+                assert_eq!(shape.len(), rotated.len());
+                assert_eq!(shape.len(), rotated.mirror().len());
+                assert_eq!(shape.width() as usize * shape.height() as usize, {
+                    rotated.width() as usize * rotated.height() as usize
+                });
+                if orientation.rotations() % 2 == 1 {
+                    assert_eq!(
+                        (shape.width(), shape.height()),
+                        (rotated.height(), rotated.width())
+                    );
+                }
+            }
+            assert_eq!(shape, shape.rotate_to(Orientation::West).rotate());
+            assert_eq!(shape.rotate_to(Orientation::South), shape.rotate().rotate());
+            assert_eq!(shape, shape.mirror().mirror());
+        }
+    }
+
+    #[test]
+    fn test_ship_definition() -> Result<()> {
+        let definitions = ShipDefinition::parse_all(
+            r#"
+            ; comment
+            ship = Ell
+            glyph = L
+            count = 2
+            rotate = true
+            mirror = true
+            #.
+            #.
+            ##
+
+            ship = Plus
+            rotate = true
+            .#.
+            ###
+            .#.
+            "#,
+        )?;
+        assert_eq!(2, definitions.len());
+        assert_eq!("Ell", definitions[0].name);
+        assert_eq!('L', definitions[0].glyph);
+        assert_eq!(2, definitions[0].count);
+        assert_eq!(8, definitions[0].variants().len());
+        assert_eq!("Plus", definitions[1].name);
+        assert_eq!('?', definitions[1].glyph);
+        assert_eq!(1, definitions[1].variants().len());
+        assert_eq!(5, definitions[1].random_variant().len());
+        assert!(ShipDefinition::parse_all("").is_err());
+        assert!(ShipDefinition::parse_all("#").is_err());
+        assert!(ShipDefinition::parse_all("ship = Empty").is_err());
+        assert!(ShipDefinition::parse_all("ship = X\ncount = many\n#").is_err());
+        assert!(ShipDefinition::parse_all("ship = X\nspeed = 10\n#").is_err());
+        assert!(ShipDefinition::parse_all("ship = X\nglyph = XL\n#").is_err());
+        for glyph in &["\u{7}", "\u{200b}", "\u{202e}", "船", "🚢"] {
+            let definition = format!("ship = X\nglyph = {}\n#", glyph);
+            assert!(ShipDefinition::parse_all(&definition).is_err());
+        }
+        let shape = Shape::from_mask(&[&format!("#{}#", ".".repeat(253))])?;
+        assert_eq!((255, 1), (shape.width(), shape.height()));
+        assert_eq!(shape, shape.mirror());
+        assert_eq!((1, 255), (shape.rotate().width(), shape.rotate().height()));
+        let definition = ShipDefinition {
+            rotate: true,
+            mirror: true,
+            ..ShipDefinition::new("Wide", 'W', shape)
+        };
+        assert_eq!(2, definition.variants().len());
+        let shape = Shape::from_mask(&vec!["#"; 255])?;
+        assert_eq!(255, shape.rotate().width());
+        assert!(Shape::from_mask(&[&"#".repeat(256)]).is_err());
+        assert!(Shape::from_mask(&[&format!("{}#", ".".repeat(255))]).is_err());
+        assert!(Shape::from_mask(&vec!["#"; 256]).is_err());
+        Ok(())
+    }
+}
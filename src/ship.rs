@@ -1,7 +1,11 @@
 //! Ship.
 
 use crate::grid::Coordinate;
+use crate::shape::{Shape, ShipDefinition};
+use std::error::Error as StdError;
 use std::fmt;
+use std::result::Result as StdResult;
+use std::str::FromStr;
 
 /// The character that represents a boat.
 const BOAT: &str = "△";
@@ -11,14 +15,99 @@ const DESTROYER_VERTICAL: &str = "▯";
 const DESTROYER_HORIZONTAL: &str = "▭";
 /// The character that represents a part of a battleship.
 const BATTLESHIP: &str = "▧";
+/// The character that represents a part of a carrier.
+const CARRIER: &str = "▩";
+/// The character that represents a part of a classic battleship.
+const CLASSIC_BATTLESHIP: &str = "▨";
+/// The character that represents a part of a cruiser.
+const CRUISER: &str = "▤";
+/// The character that represents a part of a submarine.
+const SUBMARINE: &str = "◍";
+/// The default character that represents a part of a custom ship.
+const CUSTOM: &str = "◆";
 
 /// Available orientations for the ship.
+///
+/// Orientation is the clockwise rotation of the ship shape
+/// where the ships are defined as facing north (vertically).
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Orientation {
-    /// Vertical placement.
-    Vertical,
-    /// Horizontal placement.
-    Horizontal,
+    /// Vertical placement (0 degrees).
+    North,
+    /// Horizontal placement (90 degrees).
+    East,
+    /// Vertical placement (180 degrees).
+    South,
+    /// Horizontal placement (270 degrees).
+    West,
+}
+
+/// Default orientation is vertical.
+impl Default for Orientation {
+    fn default() -> Self {
+        Self::North
+    }
+}
+
+/// Display the orientation as a direction, e.g. "north".
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::North => "north",
+                Self::East => "east",
+                Self::South => "south",
+                Self::West => "west",
+            }
+        )
+    }
+}
+
+/// For parsing the orientation from the user input.
+///
+/// Supports "h"/"v" for horizontal/vertical, "n"/"e"/"s"/"w" for
+/// directions and "0"/"90"/"180"/"270" for degrees.
+impl FromStr for Orientation {
+    type Err = Box<dyn StdError>;
+    fn from_str(value: &str) -> StdResult<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "v" | "vertical" | "n" | "north" | "0" => Ok(Self::North),
+            "h" | "horizontal" | "e" | "east" | "90" => Ok(Self::East),
+            "s" | "south" | "180" => Ok(Self::South),
+            "w" | "west" | "270" => Ok(Self::West),
+            _ => Err(format!("Invalid orientation: {}", value).into()),
+        }
+    }
+}
+
+impl Orientation {
+    /// Returns the possible variants for [`Orientation`].
+    pub fn variants() -> Vec<Self> {
+        vec![Self::North, Self::East, Self::South, Self::West]
+    }
+
+    /// Returns a random [`Orientation`].
+    pub fn new_random() -> Self {
+        let orientations = Self::variants();
+        orientations[fastrand::usize(..orientations.len())]
+    }
+
+    /// Returns the number of 90 degree clockwise rotations.
+    pub fn rotations(&self) -> usize {
+        match self {
+            Self::North => 0,
+            Self::East => 1,
+            Self::South => 2,
+            Self::West => 3,
+        }
+    }
+
+    /// Returns whether if the orientation is horizontal.
+    pub fn is_horizontal(&self) -> bool {
+        *self == Self::East || *self == Self::West
+    }
 }
 
 /// Available ship types.
@@ -30,6 +119,18 @@ pub enum ShipType {
     Destroyer(Orientation),
     /// 3x2 or 2x3 ship.
     Battleship(Orientation),
+    /// 1x5 or 5x1 ship.
+    Carrier(Orientation),
+    /// 1x4 or 4x1 ship.
+    ClassicBattleship(Orientation),
+    /// 1x3 or 3x1 ship.
+    Cruiser(Orientation),
+    /// 1x3 or 3x1 ship.
+    Submarine(Orientation),
+    /// Ship with a custom shape.
+    ///
+    /// See [`ShipDefinition`].
+    Custom,
 }
 
 /// Default ship is Boat because it is smol ^_^
@@ -49,15 +150,30 @@ impl fmt::Display for ShipType {
                 ShipType::Boat => {
                     BOAT
                 }
-                ShipType::Destroyer(Orientation::Vertical) => {
-                    DESTROYER_VERTICAL
-                }
-                ShipType::Destroyer(Orientation::Horizontal) => {
+                ShipType::Destroyer(orientation) if orientation.is_horizontal() => {
                     DESTROYER_HORIZONTAL
                 }
+                ShipType::Destroyer(_) => {
+                    DESTROYER_VERTICAL
+                }
                 ShipType::Battleship(_) => {
                     BATTLESHIP
                 }
+                ShipType::Carrier(_) => {
+                    CARRIER
+                }
+                ShipType::ClassicBattleship(_) => {
+                    CLASSIC_BATTLESHIP
+                }
+                ShipType::Cruiser(_) => {
+                    CRUISER
+                }
+                ShipType::Submarine(_) => {
+                    SUBMARINE
+                }
+                ShipType::Custom => {
+                    CUSTOM
+                }
             }
         )
     }
@@ -68,10 +184,10 @@ impl ShipType {
     pub fn variants() -> Vec<Self> {
         vec![
             Self::Boat,
-            Self::Destroyer(Orientation::Vertical),
-            Self::Destroyer(Orientation::Horizontal),
-            Self::Battleship(Orientation::Vertical),
-            Self::Battleship(Orientation::Horizontal),
+            Self::Destroyer(Orientation::North),
+            Self::Destroyer(Orientation::East),
+            Self::Battleship(Orientation::North),
+            Self::Battleship(Orientation::East),
         ]
     }
 
@@ -81,83 +197,98 @@ impl ShipType {
         types[fastrand::usize(..types.len())]
     }
 
+    /// Returns the name of the ship.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Boat => "Boat",
+            Self::Destroyer(_) => "Destroyer",
+            Self::Battleship(_) | Self::ClassicBattleship(_) => "Battleship",
+            Self::Carrier(_) => "Carrier",
+            Self::Cruiser(_) => "Cruiser",
+            Self::Submarine(_) => "Submarine",
+            Self::Custom => "Custom",
+        }
+    }
+
+    /// Returns the shape of the ship.
+    ///
+    /// Ships are defined vertically and rotated based on their orientation.
+    pub fn shape(&self) -> Shape {
+        let (shape, orientation) = match self {
+            Self::Boat | Self::Custom => (Shape::rectangle(1, 1), Orientation::default()),
+            Self::Destroyer(orientation) => (Shape::rectangle(1, 2), *orientation),
+            Self::Battleship(orientation) => (Shape::rectangle(2, 3), *orientation),
+            Self::Carrier(orientation) => (Shape::rectangle(1, 5), *orientation),
+            Self::ClassicBattleship(orientation) => (Shape::rectangle(1, 4), *orientation),
+            Self::Cruiser(orientation) | Self::Submarine(orientation) => {
+                (Shape::rectangle(1, 3), *orientation)
+            }
+        };
+        shape.rotate_to(orientation)
+    }
+
     /// Returns the hit box of the ship.
     ///
-    /// Hit are is based on the ship size.
+    /// Hit are is based on the ship shape.
+    /// Also see [`Shape::place`].
     pub fn get_hitbox(&self, coordinate: Coordinate) -> Vec<Coordinate> {
-        match self {
-            Self::Boat => {
-                vec![coordinate]
-            }
-            Self::Destroyer(orientation) => {
-                vec![coordinate, {
-                    let mut coord = coordinate;
-                    match orientation {
-                        Orientation::Vertical => {
-                            coord.y += 1;
-                        }
-                        Orientation::Horizontal => {
-                            coord.x += 1;
-                        }
-                    }
-                    coord
-                }]
-            }
-            Self::Battleship(orientation) => {
-                let mut coordinates = Vec::new();
-                for i in 0..2 {
-                    let mut coordinate = coordinate;
-                    match orientation {
-                        Orientation::Vertical => {
-                            coordinate.x += i;
-                        }
-                        Orientation::Horizontal => {
-                            coordinate.y += i;
-                        }
-                    }
-                    for j in 0..3 {
-                        coordinates.push({
-                            let mut coordinate = coordinate;
-                            match orientation {
-                                Orientation::Vertical => {
-                                    coordinate.y += j;
-                                }
-                                Orientation::Horizontal => {
-                                    coordinate.x += j;
-                                }
-                            }
-                            coordinate
-                        });
-                    }
-                }
-                coordinates
-            }
-        }
+        self.shape().place(coordinate)
     }
 }
 
 /// Representation of a ship.
-#[derive(Default, Debug)]
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct Ship {
     /// Ship type.
     pub type_: ShipType,
     /// Coordinates of the ship.
     pub coords: Vec<Coordinate>,
+    /// Name of the ship.
+    pub name: String,
+    /// The character that represents a part of the ship.
+    pub glyph: String,
 }
 
 impl Ship {
     /// Constructs a new instance of [`Ship`].
     pub fn new(type_: ShipType, coords: Vec<Coordinate>) -> Self {
-        Self { type_, coords }
+        Self {
+            type_,
+            coords,
+            name: type_.name().to_string(),
+            glyph: type_.to_string(),
+        }
+    }
+
+    /// Constructs a new instance of [`Ship`] from the given definition.
+    pub fn from_definition(definition: &ShipDefinition, coords: Vec<Coordinate>) -> Self {
+        Self {
+            type_: ShipType::Custom,
+            coords,
+            name: definition.name.to_string(),
+            glyph: definition.glyph.to_string(),
+        }
     }
 
     /// Constructs a new instance of [`Ship`] with random properties.
     pub fn new_random(max_x: u8, max_y: u8) -> Self {
-        let ship_type = ShipType::new_random();
+        Self::new_random_with_type(ShipType::new_random(), max_x, max_y)
+    }
+
+    /// Constructs a new instance of [`Ship`] with the given type at a random position.
+    pub fn new_random_with_type(ship_type: ShipType, max_x: u8, max_y: u8) -> Self {
         let coordinate = Coordinate::from((fastrand::u8(1..=max_x), fastrand::u8(1..=max_y)));
         Self::new(ship_type, ship_type.get_hitbox(coordinate))
     }
 
+    /// Constructs a new instance of [`Ship`] from the given definition at a random position.
+    ///
+    /// Also see [`ShipDefinition::random_variant`].
+    pub fn new_random_from_definition(definition: &ShipDefinition, max_x: u8, max_y: u8) -> Self {
+        let coordinate = Coordinate::from((fastrand::u8(1..=max_x), fastrand::u8(1..=max_y)));
+        Self::from_definition(definition, definition.random_variant().place(coordinate))
+    }
+
     /// Returns whether if the ship is sunk.
     pub fn is_sunk(&self) -> bool {
         self.coords.iter().all(|c| c.is_hit)
@@ -167,6 +298,8 @@ impl Ship {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::grid::Grid;
+    use crate::Result;
 
     #[test]
     fn test_ship_type() {
@@ -174,15 +307,15 @@ mod tests {
         assert_eq!(BOAT, ShipType::Boat.to_string());
         assert_eq!(
             DESTROYER_VERTICAL,
-            ShipType::Destroyer(Orientation::Vertical).to_string()
+            ShipType::Destroyer(Orientation::North).to_string()
         );
         assert_eq!(
             DESTROYER_HORIZONTAL,
-            ShipType::Destroyer(Orientation::Horizontal).to_string()
+            ShipType::Destroyer(Orientation::East).to_string()
         );
         assert_eq!(
             BATTLESHIP,
-            ShipType::Battleship(Orientation::Vertical).to_string()
+            ShipType::Battleship(Orientation::North).to_string()
         );
         assert_eq!(
             vec![Coordinate::from((1, 1))],
@@ -190,22 +323,22 @@ mod tests {
         );
         assert_eq!(
             vec![Coordinate::from((1, 1)), Coordinate::from((1, 2))],
-            ShipType::Destroyer(Orientation::Vertical).get_hitbox(Coordinate::from((1, 1)))
+            ShipType::Destroyer(Orientation::North).get_hitbox(Coordinate::from((1, 1)))
         );
         assert_eq!(
             vec![Coordinate::from((1, 1)), Coordinate::from((2, 1))],
-            ShipType::Destroyer(Orientation::Horizontal).get_hitbox(Coordinate::from((1, 1)))
+            ShipType::Destroyer(Orientation::East).get_hitbox(Coordinate::from((1, 1)))
         );
         assert_eq!(
             vec![
                 Coordinate::from((1, 1)),
-                Coordinate::from((1, 2)),
-                Coordinate::from((1, 3)),
                 Coordinate::from((2, 1)),
+                Coordinate::from((1, 2)),
                 Coordinate::from((2, 2)),
+                Coordinate::from((1, 3)),
                 Coordinate::from((2, 3))
             ],
-            ShipType::Battleship(Orientation::Vertical).get_hitbox(Coordinate::from((1, 1)))
+            ShipType::Battleship(Orientation::North).get_hitbox(Coordinate::from((1, 1)))
         );
         assert_eq!(
             vec![
@@ -216,8 +349,151 @@ mod tests {
                 Coordinate::from((2, 2)),
                 Coordinate::from((3, 2))
             ],
-            ShipType::Battleship(Orientation::Horizontal).get_hitbox(Coordinate::from((1, 1)))
+            ShipType::Battleship(Orientation::East).get_hitbox(Coordinate::from((1, 1)))
+        );
+        assert_eq!(
+            vec![Coordinate::from((255, 1)), Coordinate::from((0, 1))],
+            ShipType::Destroyer(Orientation::East).get_hitbox(Coordinate::from((255, 1)))
+        );
+        assert!(ShipType::Battleship(Orientation::East)
+            .get_hitbox(Coordinate::from((255, 1)))
+            .iter()
+            .all(|coord| coord.x == 255 || coord.x == 0));
+    }
+
+    #[test]
+    fn test_classic_ship_type() {
+        for (ship_type, glyph, name, length) in [
+            (ShipType::Carrier(Orientation::North), CARRIER, "Carrier", 5),
+            (
+                ShipType::ClassicBattleship(Orientation::East),
+                CLASSIC_BATTLESHIP,
+                "Battleship",
+                4,
+            ),
+            (ShipType::Cruiser(Orientation::North), CRUISER, "Cruiser", 3),
+            (
+                ShipType::Submarine(Orientation::East),
+                SUBMARINE,
+                "Submarine",
+                3,
+            ),
+            (
+                ShipType::Destroyer(Orientation::North),
+                DESTROYER_VERTICAL,
+                "Destroyer",
+                2,
+            ),
+        ] {
+            assert_eq!(glyph, ship_type.to_string());
+            assert_eq!(name, ship_type.name());
+            assert_eq!(length, ship_type.get_hitbox(Coordinate::from((1, 1))).len());
+        }
+        assert_eq!(
+            vec![
+                Coordinate::from((2, 3)),
+                Coordinate::from((2, 4)),
+                Coordinate::from((2, 5)),
+                Coordinate::from((2, 6)),
+                Coordinate::from((2, 7))
+            ],
+            ShipType::Carrier(Orientation::North).get_hitbox(Coordinate::from((2, 3)))
+        );
+        assert_eq!(
+            vec![
+                Coordinate::from((2, 3)),
+                Coordinate::from((3, 3)),
+                Coordinate::from((4, 3))
+            ],
+            ShipType::Cruiser(Orientation::East).get_hitbox(Coordinate::from((2, 3)))
+        );
+    }
+
+    #[test]
+    fn test_orientation() -> Result<()> {
+        assert_eq!(Orientation::North, Orientation::default());
+        for (value, orientation) in vec![
+            ("v", Orientation::North),
+            ("Vertical", Orientation::North),
+            ("h", Orientation::East),
+            ("horizontal", Orientation::East),
+            ("n", Orientation::North),
+            ("E", Orientation::East),
+            (" s ", Orientation::South),
+            ("w", Orientation::West),
+            ("west", Orientation::West),
+            ("180", Orientation::South),
+        ] {
+            assert_eq!(orientation, value.parse()?);
+        }
+        assert!("x".parse::<Orientation>().is_err());
+        assert!("".parse::<Orientation>().is_err());
+        assert!(Orientation::West.is_horizontal());
+        assert!(!Orientation::South.is_horizontal());
+        assert_eq!(
+            DESTROYER_VERTICAL,
+            ShipType::Destroyer(Orientation::South).to_string()
+        );
+        assert_eq!(
+            DESTROYER_HORIZONTAL,
+            ShipType::Destroyer(Orientation::West).to_string()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ship_rotation_properties() {
+        let ship_types: Vec<fn(Orientation) -> ShipType> = vec![
+            ShipType::Destroyer,
+            ShipType::Battleship,
+            ShipType::Carrier,
+            ShipType::ClassicBattleship,
+            ShipType::Cruiser,
+            ShipType::Submarine,
+        ];
+        for ship_type in ship_types {
+            let cells = ship_type(Orientation::North).shape().len();
+            for _ in 0..20 {
+                let orientation = Orientation::new_random();
+                let coordinate = Coordinate::from((fastrand::u8(1..=10), fastrand::u8(1..=10)));
+                assert_eq!(cells, ship_type(orientation).shape().len());
+                assert_eq!(cells, ship_type(orientation).get_hitbox(coordinate).len());
+            }
+            assert_eq!(
+                ship_type(Orientation::North).shape(),
+                ship_type(Orientation::South).shape()
+            );
+            assert_eq!(
+                ship_type(Orientation::East).shape(),
+                ship_type(Orientation::West).shape()
+            );
+        }
+    }
+
+    #[test]
+    fn test_custom_ship() -> Result<()> {
+        let mut definition = ShipDefinition::new("Tee", 'T', Shape::from_mask(&["###", ".#."])?);
+        assert_eq!(1, definition.variants().len());
+        definition.rotate = true;
+        assert_eq!(4, definition.variants().len());
+        let ship = Ship::new_random_from_definition(&definition, 10, 10);
+        assert_eq!(ShipType::Custom, ship.type_);
+        assert_eq!(("Tee", "T"), (ship.name.as_str(), ship.glyph.as_str()));
+        assert_eq!(4, ship.coords.len());
+
+        // Ships at the grid edge do not wrap around to the other edge.
+        let definition = ShipDefinition::new("Gap", 'G', Shape::from_mask(&["#.#"])?);
+        let mut grid = Grid::new(255, 1);
+        let coords = definition.shape.place(Coordinate::from((255, 1)));
+        assert!(!grid.place_ship(Ship::from_definition(&definition, coords)));
+        let coords = definition.shape.place(Coordinate::from((253, 1)));
+        assert!(grid.place_ship(Ship::from_definition(&definition, coords)));
+        let ship = Ship::new(ShipType::Cruiser(Orientation::North), Vec::new());
+        assert_eq!(
+            ("Cruiser", CRUISER),
+            (ship.name.as_str(), ship.glyph.as_str())
         );
+        Ok(())
     }
 
     #[test]
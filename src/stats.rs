@@ -0,0 +1,360 @@
+//! Statistics of the recorded games.
+
+use crate::grid::{column_name, CellState};
+use crate::record::{Record, RECORD_EXTENSION};
+use crate::{log, Result};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+/// Characters that represent the heat levels from the coldest to the hottest.
+const HEAT_LEVELS: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+/// Statistics of a player across the recorded games.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerStats {
+    /// Name of the player.
+    pub name: String,
+    /// Number of the games played.
+    pub games: u32,
+    /// Number of the games won.
+    pub wins: u32,
+    /// Number of the cells that are shot at.
+    pub shots: u32,
+    /// Number of the shots that hit a ship.
+    pub hits: u32,
+    /// Total number of the shots in the games won.
+    pub shots_to_win: u32,
+    /// Longest streak of wins.
+    pub longest_streak: u32,
+    /// Current streak of wins.
+    streak: u32,
+}
+
+impl PlayerStats {
+    /// Returns the ratio of the hits to the shots.
+    pub fn accuracy(&self) -> f64 {
+        if self.shots == 0 {
+            0.0
+        } else {
+            f64::from(self.hits) / f64::from(self.shots)
+        }
+    }
+
+    /// Returns the average number of the shots in the games won.
+    pub fn average_shots_to_win(&self) -> Option<f64> {
+        if self.wins == 0 {
+            None
+        } else {
+            Some(f64::from(self.shots_to_win) / f64::from(self.wins))
+        }
+    }
+}
+
+/// Aggregate statistics that are computed from the game records.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// Number of the games.
+    pub games: u32,
+    /// Statistics of the players ordered by wins and accuracy.
+    pub players: Vec<PlayerStats>,
+    /// Number of the hits on each cell as `(x, y)`.
+    pub heat_map: HashMap<(u8, u8), u32>,
+}
+
+/// Escapes the given value for a CSV field.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl Stats {
+    /// Computes the statistics from the given records.
+    ///
+    /// Records are processed in the order of their start time for the streaks.
+    pub fn from_records(records: &[Record]) -> Self {
+        let mut records = records.iter().collect::<Vec<&Record>>();
+        records.sort_by_key(|record| record.started);
+        let mut stats = Self::default();
+        for record in records {
+            stats.games += 1;
+            let mut indexes = Vec::new();
+            for (i, player) in record.players.iter().enumerate() {
+                let index = match stats.players.iter().position(|p| p.name == player.name) {
+                    Some(index) => index,
+                    None => {
+                        stats.players.push(PlayerStats {
+                            name: player.name.to_string(),
+                            ..PlayerStats::default()
+                        });
+                        stats.players.len() - 1
+                    }
+                };
+                let player = &mut stats.players[index];
+                player.games += 1;
+                if record.winner == Some(i) {
+                    player.wins += 1;
+                    player.streak += 1;
+                    player.longest_streak = player.longest_streak.max(player.streak);
+                } else {
+                    player.streak = 0;
+                }
+                indexes.push(index);
+            }
+            for turn in record.turns.iter().filter(|turn| turn.command.is_shot()) {
+                let hits = turn
+                    .results
+                    .iter()
+                    .filter(|(_, state)| *state == CellState::Hit || *state == CellState::Sunk)
+                    .collect::<Vec<_>>();
+                for (coordinate, _) in &hits {
+                    *stats
+                        .heat_map
+                        .entry((coordinate.x, coordinate.y))
+                        .or_insert(0) += 1;
+                }
+                if let Some(index) = indexes.get(turn.player) {
+                    let player = &mut stats.players[*index];
+                    player.shots += turn.results.len() as u32;
+                    player.hits += hits.len() as u32;
+                    if record.winner == Some(turn.player) {
+                        player.shots_to_win += turn.results.len() as u32;
+                    }
+                }
+            }
+        }
+        stats.players.sort_by(|a, b| {
+            b.wins.cmp(&a.wins).then(
+                b.accuracy()
+                    .partial_cmp(&a.accuracy())
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        });
+        stats
+    }
+
+    /// Computes the statistics from the records in the given directory.
+    ///
+    /// Files that cannot be parsed as a record are skipped.
+    pub fn load<P: AsRef<Path>>(directory: P) -> Result<Self> {
+        let mut records = Vec::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|v| v.to_str()) != Some(RECORD_EXTENSION) {
+                continue;
+            }
+            match Record::load(&path) {
+                Ok(record) => records.push(record),
+                Err(e) => log!(Warn, "Skipping record: {}", e),
+            }
+        }
+        Ok(Self::from_records(&records))
+    }
+
+    /// Returns the leaderboard and the heat map as a table.
+    pub fn as_string(&self) -> String {
+        let mut s = format!("Leaderboard ({} games)\n", self.games);
+        let name_width = self
+            .players
+            .iter()
+            .map(|player| player.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        let _ = writeln!(
+            s,
+            "{:<4} {:<width$} {:>5} {:>4} {:>8} {:>13} {:>6}",
+            "#",
+            "Name",
+            "Games",
+            "Wins",
+            "Accuracy",
+            "Shots to win",
+            "Streak",
+            width = name_width
+        );
+        for (i, player) in self.players.iter().enumerate() {
+            let _ = writeln!(
+                s,
+                "{:<4} {:<width$} {:>5} {:>4} {:>7.1}% {:>13} {:>6}",
+                format!("{}.", i + 1),
+                player.name,
+                player.games,
+                player.wins,
+                player.accuracy() * 100.0,
+                player
+                    .average_shots_to_win()
+                    .map(|shots| format!("{:.1}", shots))
+                    .unwrap_or_else(|| String::from("-")),
+                player.longest_streak,
+                width = name_width
+            );
+        }
+        s + &self.heat_map_as_string()
+    }
+
+    /// Returns the heat map of the most hit cells.
+    ///
+    /// Cells are shaded relative to the most hit cell.
+    pub fn heat_map_as_string(&self) -> String {
+        let (width, height) = self
+            .heat_map
+            .keys()
+            .fold((0, 0), |(w, h), (x, y)| (w.max(*x), h.max(*y)));
+        let max = self.heat_map.values().max().copied().unwrap_or(0);
+        if max == 0 {
+            return String::new();
+        }
+        let column_width = column_name(width).len();
+        let row_width = height.to_string().len().max(2);
+        let mut s = String::from("\nMost hit cells:\n");
+        let _ = write!(s, "{:width$} ", "", width = row_width);
+        for x in 1..=width {
+            let _ = write!(s, "{:<width$} ", column_name(x), width = column_width);
+        }
+        s.push('\n');
+        for y in 1..=height {
+            let _ = write!(s, "{:<width$} ", y, width = row_width);
+            for x in 1..=width {
+                let hits = self.heat_map.get(&(x, y)).copied().unwrap_or(0);
+                let level = (hits * (HEAT_LEVELS.len() as u32 - 1) + max - 1) / max;
+                let _ = write!(
+                    s,
+                    "{:<width$} ",
+                    HEAT_LEVELS[level as usize],
+                    width = column_width
+                );
+            }
+            s.push('\n');
+        }
+        s
+    }
+
+    /// Returns the statistics of the players as CSV.
+    pub fn players_csv(&self) -> String {
+        let mut s = String::from(
+            "name,games,wins,losses,shots,hits,accuracy,average_shots_to_win,longest_streak\n",
+        );
+        for player in &self.players {
+            let _ = writeln!(
+                s,
+                "{},{},{},{},{},{},{:.4},{},{}",
+                csv_field(&player.name),
+                player.games,
+                player.wins,
+                player.games - player.wins,
+                player.shots,
+                player.hits,
+                player.accuracy(),
+                player
+                    .average_shots_to_win()
+                    .map(|shots| format!("{:.2}", shots))
+                    .unwrap_or_default(),
+                player.longest_streak
+            );
+        }
+        s
+    }
+
+    /// Returns the heat map as CSV.
+    pub fn heat_map_csv(&self) -> String {
+        let mut cells = self.heat_map.iter().collect::<Vec<_>>();
+        cells.sort_by_key(|((x, y), hits)| (std::cmp::Reverse(**hits), *y, *x));
+        let mut s = String::from("cell,hits\n");
+        for ((x, y), hits) in cells {
+            let _ = writeln!(s, "{}{},{}", column_name(*x), y, hits);
+        }
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Command;
+    use crate::grid::Coordinate;
+    use crate::record::{PlayerRecord, Turn};
+
+    /// Returns a record where the winner hits with every shot.
+    fn record(started: u64, winner: &str, loser: &str, shots: &[(u8, u8)]) -> Record {
+        let mut record = Record {
+            started,
+            winner: Some(0),
+            ..Record::default()
+        };
+        for name in &[winner, loser] {
+            record.players.push(PlayerRecord {
+                name: name.to_string(),
+                ships: Vec::new(),
+            });
+        }
+        for (i, shot) in shots.iter().enumerate() {
+            let coordinate = Coordinate::from(*shot);
+            record.turns.push(Turn {
+                time: started,
+                player: 0,
+                command: Command::Fire(coordinate),
+                results: vec![(coordinate, CellState::Hit)],
+            });
+            record.turns.push(Turn {
+                time: started,
+                player: 1,
+                command: Command::Sonar(coordinate),
+                results: vec![(coordinate, CellState::Detected)],
+            });
+            if i == 0 {
+                record.turns.push(Turn {
+                    time: started,
+                    player: 1,
+                    command: Command::Fire(coordinate),
+                    results: vec![(coordinate, CellState::Miss)],
+                });
+            }
+        }
+        record
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::from_records(&[
+            record(3, "alice", "orhun", &[(1, 1), (2, 1)]),
+            record(1, "alice", "orhun", &[(1, 1)]),
+            record(2, "orhun", "alice", &[(1, 1), (1, 2), (1, 3)]),
+            record(4, "alice, \"the\" admiral", "orhun", &[(2, 2)]),
+        ]);
+        assert_eq!(4, stats.games);
+        assert_eq!(
+            vec!["alice", "alice, \"the\" admiral", "orhun"],
+            stats
+                .players
+                .iter()
+                .map(|player| player.name.as_str())
+                .collect::<Vec<&str>>()
+        );
+        let alice = &stats.players[0];
+        assert_eq!((3, 2, 1), (alice.games, alice.wins, alice.longest_streak));
+        assert_eq!((4, 3), (alice.shots, alice.hits));
+        assert_eq!(Some(1.5), alice.average_shots_to_win());
+        let orhun = &stats.players[2];
+        assert_eq!((4, 1, 1), (orhun.games, orhun.wins, orhun.longest_streak));
+        assert_eq!(0.5, orhun.accuracy());
+        assert_eq!(Some(&3), stats.heat_map.get(&(1, 1)));
+
+        let stats_str = stats.as_string();
+        assert!(stats_str.starts_with("Leaderboard (4 games)\n"));
+        assert!(stats_str.contains("\n1.   alice "));
+        assert!(stats_str.contains("\n1  █ ▒ \n2  ▒ ▒ \n3  ▒ · \n"));
+        assert_eq!(
+            Some("alice,3,2,1,4,3,0.7500,1.50,1"),
+            stats.players_csv().lines().nth(1)
+        );
+        assert!(stats
+            .players_csv()
+            .contains("\n\"alice, \"\"the\"\" admiral\",1,1,0,1,1,1.0000,1.00,1\n"));
+        assert!(stats.heat_map_csv().starts_with("cell,hits\nA1,3\n"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Special weapons.
+
+use crate::rules::Mode;
+use std::fmt;
+
+/// Available special weapons.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Weapon {
+    /// Reveals whether if there is a ship in a 3x3 area.
+    Sonar,
+    /// Hits a row segment.
+    Airstrike,
+    /// Travels along a line until it hits something.
+    Torpedo,
+}
+
+impl fmt::Display for Weapon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Sonar => "sonar",
+                Self::Airstrike => "airstrike",
+                Self::Torpedo => "torpedo",
+            }
+        )
+    }
+}
+
+impl Weapon {
+    /// Returns the possible variants for [`Weapon`].
+    pub fn variants() -> Vec<Self> {
+        vec![Self::Sonar, Self::Airstrike, Self::Torpedo]
+    }
+
+    /// Returns the weapon with the given name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::variants()
+            .into_iter()
+            .find(|weapon| weapon.to_string() == name.to_lowercase())
+    }
+}
+
+/// Remaining uses of the special weapons.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ammo {
+    /// Remaining sonar pings.
+    pub sonar: u8,
+    /// Remaining airstrikes.
+    pub airstrike: u8,
+    /// Remaining torpedoes.
+    pub torpedo: u8,
+}
+
+/// Show the remaining uses, e.g. "sonar: 2, airstrike: 1, torpedo: 1"
+impl fmt::Display for Ammo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}, {}: {}, {}: {}",
+            Weapon::Sonar,
+            self.sonar,
+            Weapon::Airstrike,
+            self.airstrike,
+            Weapon::Torpedo,
+            self.torpedo
+        )
+    }
+}
+
+impl Ammo {
+    /// Constructs a new instance of [`Ammo`] for the given game mode.
+    ///
+    /// Special weapons are only available in [`Advanced`] mode.
+    ///
+    /// [`Advanced`]: Mode::Advanced
+    pub fn new(mode: Mode) -> Self {
+        match mode {
+            Mode::Standard | Mode::Mobile => Self::default(),
+            Mode::Advanced => Self {
+                sonar: 2,
+                airstrike: 1,
+                torpedo: 1,
+            },
+        }
+    }
+
+    /// Uses the given weapon.
+    ///
+    /// Returns `false` if there is no ammo left for the weapon.
+    pub fn take(&mut self, weapon: Weapon) -> bool {
+        let ammo = match weapon {
+            Weapon::Sonar => &mut self.sonar,
+            Weapon::Airstrike => &mut self.airstrike,
+            Weapon::Torpedo => &mut self.torpedo,
+        };
+        if *ammo == 0 {
+            false
+        } else {
+            *ammo -= 1;
+            true
+        }
+    }
+
+    /// Returns whether if there is no ammo left for any weapon.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ammo() {
+        assert!(Ammo::new(Mode::Standard).is_empty());
+        let mut ammo = Ammo::new(Mode::Advanced);
+        assert_eq!("sonar: 2, airstrike: 1, torpedo: 1", ammo.to_string());
+        assert!(ammo.take(Weapon::Airstrike));
+        assert!(!ammo.take(Weapon::Airstrike));
+        assert!(ammo.take(Weapon::Sonar));
+        assert!(ammo.take(Weapon::Sonar));
+        assert!(!ammo.take(Weapon::Sonar));
+        assert!(ammo.take(Weapon::Torpedo));
+        assert!(ammo.is_empty());
+        assert_eq!(Some(Weapon::Torpedo), Weapon::from_name("Torpedo"));
+        assert_eq!(None, Weapon::from_name("nuke"));
+    }
+}
@@ -0,0 +1,94 @@
+//! Load test of the server with simulated clients.
+//!
+//! Run with `cargo test --release --test load -- --ignored` (might require raising `ulimit -n`).
+
+use battleship::config::Config;
+use battleship::logger::{self, Level};
+use battleship::{bot, Result};
+use std::fs;
+use std::io::Read;
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Address of the server.
+const ADDRESS: &str = "127.0.0.1:4411";
+/// Number of the idle clients in the lobby.
+const IDLE_CLIENTS: usize = 2000;
+/// Number of the games that are played by bots.
+const GAMES: usize = 5;
+/// Maximum number of the threads of the test process.
+const MAX_THREADS: usize = 64;
+
+/// Returns the number of the threads of the current process.
+fn thread_count() -> Result<usize> {
+    let status = fs::read_to_string("/proc/self/status")?;
+    let threads = status
+        .lines()
+        .find(|line| line.starts_with("Threads:"))
+        .ok_or("thread count is not found")?;
+    Ok(threads["Threads:".len()..].trim().parse()?)
+}
+
+#[test]
+#[ignore]
+fn test_idle_clients() -> Result<()> {
+    let mut config = Config::default();
+    for (key, value) in &[
+        ("socket", ADDRESS),
+        ("grid_width", "5"),
+        ("grid_height", "5"),
+        ("countdown", "0"),
+        ("max_connections", "0"),
+        ("max_connections_per_ip", "0"),
+        ("handshake_timeout", "0"),
+        ("rate_limit", "0"),
+    ] {
+        config.set(key, value)?;
+    }
+    logger::set_level(Level::Warn);
+    thread::spawn(move || {
+        if let Err(e) = battleship::run(config, || Ok(Config::default())) {
+            panic!("server failed: {}", e);
+        }
+    });
+    thread::sleep(Duration::from_millis(500));
+
+    // Fill the lobby with clients that never enter their names.
+    let start = Instant::now();
+    let mut clients = Vec::new();
+    for _ in 0..IDLE_CLIENTS {
+        let client = TcpStream::connect(ADDRESS)?;
+        client.set_read_timeout(Some(Duration::from_secs(10)))?;
+        clients.push(client);
+    }
+    for client in &mut clients {
+        let mut buffer = [0; 512];
+        assert_ne!(0, client.read(&mut buffer)?, "client is not greeted");
+    }
+    println!(
+        "{} clients are greeted in {:?}",
+        IDLE_CLIENTS,
+        start.elapsed()
+    );
+    let threads = thread_count()?;
+    assert!(threads <= MAX_THREADS, "{} threads are running", threads);
+
+    // Games are still played while the lobby is full.
+    let start = Instant::now();
+    for game in 0..GAMES {
+        let bots = (0..2)
+            .map(|i| {
+                thread::spawn(move || {
+                    bot::play(ADDRESS, &format!("bot-{}-{}", game, i)).map_err(|e| e.to_string())
+                })
+            })
+            .collect::<Vec<_>>();
+        for bot in bots {
+            let result = bot.join().expect("bot panicked")?;
+            assert!(result.ends_with("won.") || result.ends_with("won!"));
+        }
+    }
+    println!("{} games are played in {:?}", GAMES, start.elapsed());
+    Ok(())
+}